@@ -25,6 +25,13 @@ pub enum Action {
     JumpToTop,
     JumpToBottom,
     CenterView,
+    ScrollLeft,
+    ScrollRight,
+    FileView,
+    Blame,
+    History,
+    Log,
+    Branches,
     PageUp,
     PageDown,
     YankPath,
@@ -34,6 +41,9 @@ pub enum Action {
     Redo,
     ToggleWorktrees,
     Commit,
+    Ignore,
+    Discard,
+    Review,
 }
 
 #[derive(Clone)]
@@ -73,6 +83,14 @@ impl Default for KeyConfig {
         mappings.insert(KeyCode::Char('u'), Action::Undo);
         mappings.insert(KeyCode::Char('c'), Action::Commit);
         mappings.insert(KeyCode::Char('w'), Action::ToggleWorktrees);
+        mappings.insert(KeyCode::Char('i'), Action::Ignore);
+        mappings.insert(KeyCode::Char('x'), Action::Discard);
+        mappings.insert(KeyCode::Char('R'), Action::Review);
+        mappings.insert(KeyCode::Char('o'), Action::FileView);
+        mappings.insert(KeyCode::Char('b'), Action::Blame);
+        mappings.insert(KeyCode::Char('T'), Action::History);
+        mappings.insert(KeyCode::Char('C'), Action::Log);
+        mappings.insert(KeyCode::Char('B'), Action::Branches);
         // Alt+V for Easter Egg (Option+V on Mac)
         // We'll handle modifiers specifically in event loop if needed,
         // but for config we can store it or handle it in event.rs
@@ -138,6 +156,13 @@ fn parse_action(s: &str) -> Option<Action> {
         "top" | "jump_to_top" => Some(Action::JumpToTop),
         "bottom" | "jump_to_bottom" => Some(Action::JumpToBottom),
         "center" | "center_view" => Some(Action::CenterView),
+        "scroll_left" => Some(Action::ScrollLeft),
+        "scroll_right" => Some(Action::ScrollRight),
+        "file_view" | "open_file" => Some(Action::FileView),
+        "blame" => Some(Action::Blame),
+        "history" => Some(Action::History),
+        "log" => Some(Action::Log),
+        "branches" => Some(Action::Branches),
         "page_up" => Some(Action::PageUp),
         "page_down" => Some(Action::PageDown),
         "yank" | "yank_path" => Some(Action::YankPath),
@@ -146,6 +171,9 @@ fn parse_action(s: &str) -> Option<Action> {
         "redo" => Some(Action::Redo),
         "worktrees" | "toggle_worktrees" => Some(Action::ToggleWorktrees),
         "commit" => Some(Action::Commit),
+        "ignore" => Some(Action::Ignore),
+        "discard" => Some(Action::Discard),
+        "review" => Some(Action::Review),
         _ => None,
     }
 }
@@ -1,13 +1,27 @@
 use anyhow::{Context, Result};
 use serde::Serialize;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::process::Command;
 
 use crate::node;
 use crate::parser;
 
+pub mod blame;
+pub mod branch;
+pub mod discard;
+pub mod history;
+pub mod ignore;
+pub mod log;
+pub mod options;
 pub mod patch;
-pub use patch::Hunk;
+pub mod preview;
+pub mod untracked;
+pub use discard::FileBackup;
+pub use ignore::{IgnoreCandidate, IgnoreTarget};
+pub use options::DiffOptions;
+pub use patch::{DiffFile, Hunk};
+pub use untracked::UntrackedSummary;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Worktree {
@@ -20,10 +34,17 @@ pub fn build_tree_from_git(
     staged_only: bool,
     modified_only: bool,
     untracked_only: bool,
+    show_ignored: bool,
+    options: &DiffOptions,
+    untracked: &mut UntrackedSummary,
 ) -> Result<Option<node::Node>> {
-    // Run git status --porcelain -b -u
+    let mut args = vec!["status", "--porcelain", "-b", untracked.status_arg()];
+    if show_ignored {
+        args.push("--ignored=matching");
+    }
+
     let status_output = Command::new("git")
-        .args(["status", "--porcelain", "-b", "-u"])
+        .args(args)
         .output()
         .context("Failed to execute git status")?;
 
@@ -49,16 +70,52 @@ pub fn build_tree_from_git(
         }
     }
 
+    let (lines, summaries) = untracked::split_untracked_dirs(lines, untracked)?;
+
     // Collect diff stats
     let mut stats = HashMap::new();
-    collect_diff_stats(&mut stats, &["diff", "--numstat"])?;
-    collect_diff_stats(&mut stats, &["diff", "--cached", "--numstat"])?;
+    collect_diff_stats(&mut stats, &["diff", "--numstat"], options)?;
+    collect_diff_stats(&mut stats, &["diff", "--cached", "--numstat"], options)?;
 
-    let result_node =
+    let mut result_node =
         parser::build_tree(lines, &stats, staged_only, modified_only, untracked_only)?;
+    untracked::label_summaries(&mut result_node, &summaries);
     Ok(Some(result_node))
 }
 
+/// Maps each ignored path to the rule that excludes it (`source:line:pattern`),
+/// as reported by `git check-ignore -v`.
+pub fn get_ignore_rules(paths: &[String]) -> Result<HashMap<String, String>> {
+    if paths.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let output = Command::new("git")
+        .args(["check-ignore", "-v", "--"])
+        .args(paths)
+        .output()
+        .context("Failed to execute git check-ignore")?;
+
+    // Exit code 1 just means none of the paths are ignored
+    if !output.status.success() && output.status.code() != Some(1) {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git check-ignore failed: {}", err);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_ignore_rules(&stdout))
+}
+
+fn parse_ignore_rules(stdout: &str) -> HashMap<String, String> {
+    let mut rules = HashMap::new();
+    for line in stdout.lines() {
+        if let Some((rule, path)) = line.split_once('\t') {
+            rules.insert(path.to_string(), rule.to_string());
+        }
+    }
+    rules
+}
+
 pub fn get_status_header() -> Result<String> {
     let output = Command::new("git")
         .args(["status", "--porcelain", "-b"])
@@ -79,8 +136,12 @@ pub fn get_status_header() -> Result<String> {
 pub fn collect_diff_stats(
     stats: &mut HashMap<String, (usize, usize)>,
     args: &[&str],
+    options: &DiffOptions,
 ) -> Result<()> {
-    let output = Command::new("git").args(args).output()?;
+    let output = Command::new("git")
+        .args(args)
+        .args(options.args())
+        .output()?;
 
     if output.status.success() {
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -128,19 +189,63 @@ pub fn commit(message: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn get_diff(path: &str, is_staged: bool, is_untracked: bool) -> Result<String> {
-    let mut args = vec!["diff"];
+/// The two sides a diff compares.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffMode {
+    Worktree, // Index vs worktree (`git diff`)
+    Index,    // HEAD vs index (`git diff --cached`)
+    Head,     // HEAD vs worktree (`git diff HEAD`)
+}
 
-    if is_staged {
-        args.push("--cached");
+impl DiffMode {
+    pub fn next(&self) -> Self {
+        match self {
+            DiffMode::Worktree => DiffMode::Index,
+            DiffMode::Index => DiffMode::Head,
+            DiffMode::Head => DiffMode::Worktree,
+        }
     }
 
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiffMode::Worktree => "Index → Worktree",
+            DiffMode::Index => "HEAD → Index",
+            DiffMode::Head => "HEAD → Worktree",
+        }
+    }
+
+    /// Whether the new side is the worktree, so hunks can be discarded from it.
+    pub fn shows_worktree(&self) -> bool {
+        *self != DiffMode::Index
+    }
+
+    fn args(&self) -> &'static [&'static str] {
+        match self {
+            DiffMode::Worktree => &[],
+            DiffMode::Index => &["--cached"],
+            DiffMode::Head => &["HEAD"],
+        }
+    }
+}
+
+pub fn get_diff(
+    path: &str,
+    mode: DiffMode,
+    is_untracked: bool,
+    options: &DiffOptions,
+) -> Result<String> {
+    let mut args = vec!["diff".to_string()];
+    args.extend(options.args());
+
     if is_untracked {
-        args.push("--no-index");
-        args.push("/dev/null");
+        args.push("--no-index".to_string());
+        args.push("/dev/null".to_string());
+    } else {
+        args.extend(mode.args().iter().map(|a| a.to_string()));
+        args.push("--".to_string());
     }
 
-    args.push(path);
+    args.push(path.to_string());
 
     let output = Command::new("git").args(args).output()?;
 
@@ -153,6 +258,134 @@ pub fn get_diff(path: &str, is_staged: bool, is_untracked: bool) -> Result<Strin
     }
 }
 
+/// Diff of every tracked file under `paths` (the whole tree when empty), as one
+/// multi-file diff. Untracked files are not included, just like `git add -p`.
+pub fn get_diff_paths(paths: &[String], mode: DiffMode, options: &DiffOptions) -> Result<String> {
+    let output = Command::new("git")
+        .arg("diff")
+        .args(options.args())
+        .args(mode.args())
+        .arg("--")
+        .args(paths)
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git diff failed: {}", err)
+    }
+}
+
+/// `git show` of `commit`: its message followed by its diff, limited to
+/// `paths` unless empty. Merges show what they brought into the first parent.
+pub fn show_commit(commit: &str, paths: &[String], options: &DiffOptions) -> Result<String> {
+    let output = Command::new("git")
+        .args(["show", "--diff-merges=first-parent"])
+        .args(options.args())
+        .arg(commit)
+        .arg("--")
+        .args(paths)
+        .output()
+        .context("Failed to execute git show")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git show failed: {}", err);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Like `get_diff_paths`, but when the worktree is shown also appends the
+/// untracked files under `paths` as new-file diffs, so a directory shows
+/// everything in it. Files too large or binary to diff get a one-line stub.
+pub fn get_combined_diff(
+    paths: &[String],
+    mode: DiffMode,
+    options: &DiffOptions,
+) -> Result<String> {
+    let mut content = get_diff_paths(paths, mode, options)?;
+    if mode.shows_worktree() {
+        for path in get_untracked_files(paths)? {
+            if preview::needs_preview(&path)? {
+                content.push_str(&preview::diff_stub(&path)?);
+            } else {
+                content.push_str(&get_diff(&path, mode, true, options)?);
+            }
+        }
+    }
+    Ok(content)
+}
+
+/// Untracked, non-ignored files under `paths`.
+pub fn get_untracked_files(paths: &[String]) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["ls-files", "--others", "--exclude-standard", "--"])
+        .args(paths)
+        .output()
+        .context("Failed to execute git ls-files")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git ls-files failed: {}", err);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|s| s.to_string())
+        .collect())
+}
+
+/// Content of `path` (relative to the repository root) at `rev`, read with
+/// `git show <rev>:<path>`. An empty `rev` reads the index.
+pub fn show_file(rev: &str, path: &str) -> Result<String> {
+    let bytes = show_file_bytes(rev, path)?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Raw content of `path` at `rev`, for binary files such as images.
+pub fn show_file_bytes(rev: &str, path: &str) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("show")
+        .arg(format!("{}:{}", rev, path))
+        .output()
+        .context("Failed to execute git show")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git show failed: {}", err);
+    }
+    Ok(output.stdout)
+}
+
+/// Content of `path` (relative to the repository root) in the worktree.
+pub fn read_worktree_file(path: &str) -> Result<String> {
+    let bytes = read_worktree_bytes(path)?;
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+pub fn read_worktree_bytes(path: &str) -> Result<Vec<u8>> {
+    let file = rev_parse(&["--show-toplevel"])?.join(path);
+    std::fs::read(&file).with_context(|| format!("Failed to read {}", path))
+}
+
+/// Runs `git rev-parse` with `args` and returns the single path it prints
+/// (e.g. `--show-toplevel` or `--git-path <name>`).
+pub fn rev_parse(args: &[&str]) -> Result<PathBuf> {
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .args(args)
+        .output()
+        .context("Failed to execute git rev-parse")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git rev-parse failed: {}", err);
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
+
 pub fn get_config(key: &str) -> Option<String> {
     let output = Command::new("git")
         .args(["config", "--global", key])
@@ -170,6 +403,53 @@ pub fn get_config(key: &str) -> Option<String> {
         None
     }
 }
+
+/// Reads `key` from the effective config (repository, global and system files),
+/// for git's own settings such as `diff.wordRegex`.
+pub fn get_repo_config(key: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "--get", key])
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        let s = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if s.is_empty() {
+            None
+        } else {
+            Some(s)
+        }
+    } else {
+        None
+    }
+}
+
+pub fn set_config(key: &str, value: &str) -> Result<()> {
+    let output = Command::new("git")
+        .args(["config", "--global", key, value])
+        .output()
+        .context("Failed to execute git config")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git config failed: {}", err);
+    }
+    Ok(())
+}
+
+/// The editor git itself would open: `GIT_EDITOR`, `core.editor`, `VISUAL`
+/// or `EDITOR`, as resolved by `git var`.
+pub fn get_editor() -> String {
+    Command::new("git")
+        .args(["var", "GIT_EDITOR"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|editor| !editor.is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
 pub fn get_config_regexp(pattern: &str) -> HashMap<String, String> {
     let mut map = HashMap::new();
     let output = Command::new("git")
@@ -255,6 +535,16 @@ fn parse_worktrees(stdout: &str) -> Vec<Worktree> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_diff_mode_cycle() {
+        let mode = DiffMode::Worktree;
+        assert_eq!(mode.next(), DiffMode::Index);
+        assert_eq!(mode.next().next(), DiffMode::Head);
+        assert_eq!(mode.next().next().next(), DiffMode::Worktree);
+        assert!(!DiffMode::Index.shows_worktree());
+        assert!(DiffMode::Head.shows_worktree());
+    }
+
     #[test]
     fn test_parse_worktrees() {
         let output = "worktree /path/to/repo\nHEAD 123456\nbranch refs/heads/main\n\nworktree /path/to/other\nHEAD abcdef\nbranch refs/heads/dev\n";
@@ -265,4 +555,13 @@ mod tests {
         assert_eq!(worktrees[1].path, "/path/to/other");
         assert_eq!(worktrees[1].branch, "refs/heads/dev");
     }
+
+    #[test]
+    fn test_parse_ignore_rules() {
+        let output = ".gitignore:1:*.log\ta.log\n.gitignore:2:build/\tbuild/\n";
+        let rules = parse_ignore_rules(output);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules["a.log"], ".gitignore:1:*.log");
+        assert_eq!(rules["build/"], ".gitignore:2:build/");
+    }
 }
@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Who last touched one line of a file, from `git blame --porcelain`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlameLine {
+    pub commit: String,
+    pub author: String,
+    pub time: i64, // Unix time, shifted into the author's timezone
+    pub summary: String,
+    pub text: String,
+}
+
+impl BlameLine {
+    /// Lines changed in the worktree or index blame to the all-zero commit.
+    pub fn is_uncommitted(&self) -> bool {
+        self.commit.bytes().all(|b| b == b'0')
+    }
+
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(7)]
+    }
+
+    /// Author date as `YYYY-MM-DD`.
+    pub fn date(&self) -> String {
+        let (y, m, d) = civil_from_days(self.time.div_euclid(86_400));
+        format!("{:04}-{:02}-{:02}", y, m, d)
+    }
+}
+
+pub fn blame(path: &str) -> Result<Vec<BlameLine>> {
+    let output = Command::new("git")
+        .args(["blame", "--porcelain", "--", path])
+        .output()
+        .context("Failed to execute git blame")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git blame failed: {}", err);
+    }
+    Ok(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Commit headers are given in full only the first time a commit shows up,
+/// so they are remembered for the lines that follow.
+fn parse_porcelain(output: &str) -> Vec<BlameLine> {
+    #[derive(Default, Clone)]
+    struct Info {
+        author: String,
+        time: i64,
+        tz: i64,
+        summary: String,
+    }
+
+    let mut commits: HashMap<String, Info> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for line in output.lines() {
+        if let Some(text) = line.strip_prefix('\t') {
+            let info = commits.get(&current).cloned().unwrap_or_default();
+            lines.push(BlameLine {
+                commit: current.clone(),
+                author: info.author,
+                time: info.time + info.tz,
+                summary: info.summary,
+                text: text.to_string(),
+            });
+            continue;
+        }
+
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        // 40 hex digits for SHA-1 repositories, 64 for SHA-256 ones
+        if (key.len() == 40 || key.len() == 64) && key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            current = key.to_string();
+            commits.entry(current.clone()).or_default();
+            continue;
+        }
+        let Some(info) = commits.get_mut(&current) else {
+            continue;
+        };
+        match key {
+            "author" => info.author = value.to_string(),
+            "author-time" => info.time = value.parse().unwrap_or(0),
+            "author-tz" => info.tz = tz_offset(value),
+            "summary" => info.summary = value.to_string(),
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// Seconds east of UTC for a `+0130` style offset.
+fn tz_offset(tz: &str) -> i64 {
+    let sign = if tz.starts_with('-') { -1 } else { 1 };
+    let digits = tz.trim_start_matches(['+', '-']);
+    let hours: i64 = digits.get(..2).and_then(|h| h.parse().ok()).unwrap_or(0);
+    let minutes: i64 = digits.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+    sign * (hours * 3600 + minutes * 60)
+}
+
+/// Year, month and day of a count of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: &str = "1111111111111111111111111111111111111111";
+    const ZERO: &str = "0000000000000000000000000000000000000000";
+
+    #[test]
+    fn test_parse_porcelain() {
+        let output = format!(
+            "{A} 1 1 2\nauthor Ada\nauthor-mail <ada@example.com>\nauthor-time 1700000000\n\
+             author-tz -0100\nsummary Add parser\nfilename src/a.rs\n\tfn main() {{\n\
+             {A} 2 2\n\t}}\n\
+             {ZERO} 3 3 1\nauthor Not Committed Yet\nauthor-time 1800000000\nauthor-tz +0000\n\
+             summary Version of src/a.rs from src/a.rs\nfilename src/a.rs\n\t// new\n"
+        );
+        let lines = parse_porcelain(&output);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].author, "Ada");
+        assert_eq!(lines[0].text, "fn main() {");
+        assert_eq!(lines[1].summary, "Add parser");
+        assert_eq!(lines[1].time, 1_700_000_000 - 3600);
+        assert_eq!(lines[1].short_commit(), "1111111");
+        assert!(!lines[1].is_uncommitted());
+        assert!(lines[2].is_uncommitted());
+
+        let sha256 = "a".repeat(64);
+        let lines = parse_porcelain(&format!(
+            "{sha256} 1 1 1\nauthor Bob\nsummary Init\nfilename b.rs\n\tx\n"
+        ));
+        assert_eq!(lines[0].commit, sha256);
+        assert_eq!(lines[0].author, "Bob");
+    }
+
+    #[test]
+    fn test_date() {
+        let line = |time| BlameLine {
+            commit: A.to_string(),
+            author: String::new(),
+            time,
+            summary: String::new(),
+            text: String::new(),
+        };
+        assert_eq!(line(0).date(), "1970-01-01");
+        assert_eq!(line(1_700_000_000).date(), "2023-11-14");
+        assert_eq!(line(951_782_400).date(), "2000-02-29");
+    }
+}
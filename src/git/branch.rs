@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+
+/// A local or remote-tracking branch, from `git for-each-ref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Branch {
+    pub name: String, // e.g. `main`, `origin/main` for remotes
+    pub is_remote: bool,
+    pub is_head: bool,
+    pub upstream: Option<String>,
+    pub upstream_gone: bool,
+    pub ahead: usize,
+    pub behind: usize,
+    pub date: String, // YYYY-MM-DD of the last commit
+    pub subject: String,
+}
+
+impl Branch {
+    /// The local branch a remote branch would be checked out as.
+    pub fn local_name(&self) -> &str {
+        if self.is_remote {
+            self.name
+                .split_once('/')
+                .map_or(&self.name, |(_, name)| name)
+        } else {
+            &self.name
+        }
+    }
+}
+
+/// Local branches, then remote ones, each sorted by name.
+pub fn list_branches() -> Result<Vec<Branch>> {
+    let output = Command::new("git")
+        .args(["for-each-ref", "refs/heads", "refs/remotes"])
+        .arg(
+            "--format=%(HEAD)%1f%(refname)%1f%(upstream:short)%1f%(upstream:track,nobracket)\
+             %1f%(committerdate:short)%1f%(subject)",
+        )
+        .output()
+        .context("Failed to execute git for-each-ref")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git for-each-ref failed: {}", err);
+    }
+    Ok(parse_branches(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_branches(output: &str) -> Vec<Branch> {
+    let mut branches: Vec<Branch> = output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\x1f').collect();
+            let [head, refname, upstream, track, date, subject] = fields[..] else {
+                return None;
+            };
+            let (name, is_remote) = if let Some(name) = refname.strip_prefix("refs/heads/") {
+                (name, false)
+            } else {
+                (refname.strip_prefix("refs/remotes/")?, true)
+            };
+            // `origin/HEAD` only points at another remote branch
+            if is_remote && name.ends_with("/HEAD") {
+                return None;
+            }
+            let (ahead, behind) = parse_track(track);
+            Some(Branch {
+                name: name.to_string(),
+                is_remote,
+                is_head: head == "*",
+                upstream: Some(upstream.to_string()).filter(|u| !u.is_empty()),
+                upstream_gone: track == "gone",
+                ahead,
+                behind,
+                date: date.to_string(),
+                subject: subject.to_string(),
+            })
+        })
+        .collect();
+    branches.sort_by(|a, b| (a.is_remote, &a.name).cmp(&(b.is_remote, &b.name)));
+    branches
+}
+
+/// Commits ahead of and behind the upstream, from `ahead 1, behind 2`.
+fn parse_track(track: &str) -> (usize, usize) {
+    let mut counts = (0, 0);
+    for part in track.split(", ") {
+        if let Some(n) = part.strip_prefix("ahead ") {
+            counts.0 = n.parse().unwrap_or(0);
+        } else if let Some(n) = part.strip_prefix("behind ") {
+            counts.1 = n.parse().unwrap_or(0);
+        }
+    }
+    counts
+}
+
+/// Switches to `branch`. A remote branch is checked out as a local branch
+/// tracking it, or as the existing local branch of the same name.
+pub fn checkout(branch: &Branch, local_exists: bool) -> Result<()> {
+    if branch.is_remote && !local_exists {
+        run(&["switch", "--track", &branch.name])
+    } else {
+        run(&["switch", branch.local_name()])
+    }
+}
+
+/// Creates `name` at `start` without switching to it.
+pub fn create(name: &str, start: &str) -> Result<()> {
+    run(&["branch", "--", name, start])
+}
+
+pub fn rename(old: &str, new: &str) -> Result<()> {
+    run(&["branch", "-m", "--", old, new])
+}
+
+/// Deletes a local branch. Without `force`, git refuses unmerged branches.
+pub fn delete(name: &str, force: bool) -> Result<()> {
+    run(&["branch", if force { "-D" } else { "-d" }, "--", name])
+}
+
+pub fn set_upstream(name: &str, upstream: &str) -> Result<()> {
+    run(&[
+        "branch",
+        &format!("--set-upstream-to={}", upstream),
+        "--",
+        name,
+    ])
+}
+
+/// Whether every commit of `name` is already in HEAD.
+pub fn is_merged(name: &str) -> Result<bool> {
+    let output = Command::new("git")
+        .args(["merge-base", "--is-ancestor", name, "HEAD"])
+        .output()
+        .context("Failed to execute git merge-base")?;
+    Ok(output.status.success())
+}
+
+/// Whether tracked files have changes a checkout would carry along or trip on.
+pub fn has_local_changes() -> Result<bool> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()
+        .context("Failed to execute git status")?;
+    Ok(!output.stdout.is_empty())
+}
+
+fn run(args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to execute git")?;
+    if !output.status.success() {
+        // Shown on one line, so git's multi-line advice is run together
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("{}", err.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_branches() {
+        let output = "\x1frefs/remotes/origin/HEAD\x1f\x1f\x1f2024-03-01\x1fTip\n\
+                      \x1frefs/remotes/origin/main\x1f\x1f\x1f2024-03-01\x1fTip\n\
+                      *\x1frefs/heads/main\x1forigin/main\x1fahead 1, behind 2\x1f2024-03-02\x1fWork\n\
+                      \x1frefs/heads/feature/x\x1forigin/feature/x\x1fgone\x1f2024-01-01\x1fOld\n";
+        let branches = parse_branches(output);
+        let names: Vec<&str> = branches.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["feature/x", "main", "origin/main"]);
+        assert!(branches[1].is_head);
+        assert_eq!((branches[1].ahead, branches[1].behind), (1, 2));
+        assert_eq!(branches[1].upstream.as_deref(), Some("origin/main"));
+        assert!(branches[0].upstream_gone);
+        assert!(branches[2].is_remote);
+        assert_eq!(branches[2].upstream, None);
+        assert_eq!(branches[2].local_name(), "main");
+        assert_eq!(branches[0].local_name(), "feature/x");
+    }
+
+    #[test]
+    fn test_parse_track() {
+        assert_eq!(parse_track("ahead 3"), (3, 0));
+        assert_eq!(parse_track("behind 4"), (0, 4));
+        assert_eq!(parse_track("ahead 1, behind 2"), (1, 2));
+        assert_eq!(parse_track("gone"), (0, 0));
+        assert_eq!(parse_track(""), (0, 0));
+    }
+}
@@ -0,0 +1,228 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Worktree content of a file before and after a discard, stored as blobs in the
+/// object database (`None` means the file did not exist). Unreferenced blobs
+/// survive until the next `git gc --prune`, which is what makes discards undoable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileBackup {
+    pub path: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorktreeChange {
+    pub path: String,
+    pub is_untracked: bool,
+}
+
+/// Lists files under `paths` (relative to the repository root) that differ
+/// between the index and the worktree.
+pub fn get_worktree_changes(paths: &[String]) -> Result<Vec<WorktreeChange>> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "-u", "--"])
+        .args(paths.iter().map(|p| match p.as_str() {
+            "." => ":(top)".to_string(), // The tree's root
+            p => format!(":(top){}", p),
+        }))
+        .output()
+        .context("Failed to execute git status")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git status failed: {}", err);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_worktree_changes(&stdout))
+}
+
+fn parse_worktree_changes(stdout: &str) -> Vec<WorktreeChange> {
+    stdout
+        .lines()
+        .filter(|line| line.len() > 3)
+        .filter_map(|line| {
+            let y = line.chars().nth(1)?;
+            if y == ' ' || y == '!' {
+                return None; // No worktree changes
+            }
+            let rest = &line[3..];
+            let path = rest.split(" -> ").last().unwrap_or(rest);
+            Some(WorktreeChange {
+                path: path.to_string(),
+                is_untracked: y == '?',
+            })
+        })
+        .collect()
+}
+
+/// Where `path`, relative to the repository root like every path
+/// `git status --porcelain` prints, is on disk, whatever the current directory.
+fn worktree_path(path: &str) -> Result<PathBuf> {
+    Ok(super::rev_parse(&["--show-toplevel"])?.join(path))
+}
+
+/// Writes the current worktree content of `path` into the object database.
+pub fn backup_file(path: &str) -> Result<Option<String>> {
+    let file = worktree_path(path)?;
+    if std::fs::symlink_metadata(&file).is_err() {
+        return Ok(None);
+    }
+
+    let output = Command::new("git")
+        .args(["hash-object", "-w", "--no-filters", "--"])
+        .arg(&file)
+        .output()
+        .context("Failed to execute git hash-object")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git hash-object failed: {}", err);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Puts `blob` back as the worktree content of `path`, or removes the file for `None`.
+pub fn restore_backup(path: &str, blob: Option<&str>) -> Result<()> {
+    let file = worktree_path(path)?;
+    match blob {
+        Some(blob) => {
+            let output = Command::new("git")
+                .args(["cat-file", "blob", blob])
+                .output()
+                .context("Failed to execute git cat-file")?;
+
+            if !output.status.success() {
+                let err = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("Backup {} is no longer available: {}", blob, err);
+            }
+
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::File::create(&file)
+                .and_then(|mut f| f.write_all(&output.stdout))
+                .with_context(|| format!("Failed to write {}", path))?;
+        }
+        None => {
+            if std::fs::symlink_metadata(&file).is_ok() {
+                std::fs::remove_file(&file)
+                    .with_context(|| format!("Failed to remove {}", path))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Throws away the worktree changes of `changes` one file at a time, stopping
+/// at the first failure. The backups of every file touched so far come back
+/// either way, so what was already discarded can still be undone.
+pub fn discard_files(changes: &[WorktreeChange]) -> (Vec<FileBackup>, Result<()>) {
+    discard_each(changes, backup_file, discard_file)
+}
+
+fn discard_each(
+    changes: &[WorktreeChange],
+    backup: impl Fn(&str) -> Result<Option<String>>,
+    discard: impl Fn(&WorktreeChange) -> Result<()>,
+) -> (Vec<FileBackup>, Result<()>) {
+    let mut backups = Vec::new();
+    for change in changes {
+        let before = match backup(&change.path) {
+            Ok(before) => before,
+            Err(e) => return (backups, Err(e)),
+        };
+        // Once the file may have changed its backup is kept, even if the
+        // discard or reading the file back fails
+        let result = discard(change);
+        let after = backup(&change.path);
+        backups.push(FileBackup {
+            path: change.path.clone(),
+            before,
+            after: after.as_ref().ok().cloned().flatten(),
+        });
+        if let Err(e) = result.and(after) {
+            return (backups, Err(e));
+        }
+    }
+    (backups, Ok(()))
+}
+
+/// Throws away the worktree changes of a single file.
+fn discard_file(change: &WorktreeChange) -> Result<()> {
+    let file = worktree_path(&change.path)?;
+    if change.is_untracked {
+        std::fs::remove_file(&file).with_context(|| format!("Failed to remove {}", change.path))?;
+    } else {
+        let output = Command::new("git")
+            .args(["restore", "--worktree", "--"])
+            .arg(&file)
+            .output()
+            .context("Failed to execute git restore")?;
+
+        if !output.status.success() {
+            let err = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Git restore failed: {}", err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_worktree_changes() {
+        let output = "M  staged.rs\n M modified.rs\nAD gone.rs\n?? new.rs\nR  a.rs -> b.rs\nRM c.rs -> d.rs\n";
+        let changes = parse_worktree_changes(output);
+        let paths: Vec<&str> = changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["modified.rs", "gone.rs", "new.rs", "d.rs"]);
+        assert!(changes[2].is_untracked);
+        assert!(!changes[0].is_untracked);
+    }
+
+    #[test]
+    fn test_discard_each_keeps_backups_on_failure() {
+        let change = |path: &str| WorktreeChange {
+            path: path.to_string(),
+            is_untracked: false,
+        };
+        let changes = vec![change("a"), change("b"), change("c")];
+        let (backups, result) = discard_each(
+            &changes,
+            |path| Ok(Some(format!("blob-{}", path))),
+            |change| {
+                if change.path == "b" {
+                    anyhow::bail!("cannot discard b");
+                }
+                Ok(())
+            },
+        );
+        assert!(result.is_err());
+        let paths: Vec<&str> = backups.iter().map(|b| b.path.as_str()).collect();
+        assert_eq!(paths, vec!["a", "b"]);
+        assert_eq!(backups[0].before.as_deref(), Some("blob-a"));
+
+        // A backup that can't be read back still keeps the one taken before
+        let hashed = std::cell::Cell::new(false);
+        let (backups, result) = discard_each(
+            &changes[..1],
+            |_| {
+                if hashed.replace(true) {
+                    anyhow::bail!("cannot hash");
+                }
+                Ok(Some("blob".to_string()))
+            },
+            |_| Ok(()),
+        );
+        assert!(result.is_err());
+        assert_eq!(backups[0].before.as_deref(), Some("blob"));
+        assert_eq!(backups[0].after, None);
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+
+/// Commits shown in a file's history at most.
+pub const HISTORY_LIMIT: usize = 1000;
+
+/// One commit in the history of a file or directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub commit: String,
+    pub author: String,
+    pub date: String, // YYYY-MM-DD
+    pub subject: String,
+    pub added: usize,
+    pub deleted: usize,
+    pub paths: Vec<String>, // What the file was called in this commit, both names for a rename
+}
+
+impl HistoryEntry {
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(7)]
+    }
+}
+
+/// Commits touching `path`, newest first. Files are followed across renames;
+/// directories list every commit that touched something underneath.
+pub fn file_history(path: &str, is_dir: bool) -> Result<Vec<HistoryEntry>> {
+    let mut cmd = Command::new("git");
+    cmd.args(["log", "--numstat", "--date=short"])
+        .arg(format!("--max-count={}", HISTORY_LIMIT))
+        .arg("--format=%x1e%H%x1f%an%x1f%ad%x1f%s");
+    if !is_dir {
+        cmd.arg("--follow");
+    }
+    let output = cmd
+        .arg("--")
+        .arg(path)
+        .output()
+        .context("Failed to execute git log")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git log failed: {}", err);
+    }
+    Ok(parse_log(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_log(output: &str) -> Vec<HistoryEntry> {
+    let mut entries = Vec::new();
+    for record in output.split('\x1e').filter(|r| !r.trim().is_empty()) {
+        let mut lines = record.lines();
+        let header: Vec<&str> = lines.next().unwrap_or("").split('\x1f').collect();
+        let [commit, author, date, subject] = header[..] else {
+            continue;
+        };
+        let mut entry = HistoryEntry {
+            commit: commit.to_string(),
+            author: author.to_string(),
+            date: date.to_string(),
+            subject: subject.to_string(),
+            added: 0,
+            deleted: 0,
+            paths: Vec::new(),
+        };
+        for line in lines {
+            let mut parts = line.splitn(3, '\t');
+            let (Some(added), Some(deleted), Some(path)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            // Binary files show `-` for both counts
+            entry.added += added.parse::<usize>().unwrap_or(0);
+            entry.deleted += deleted.parse::<usize>().unwrap_or(0);
+            for path in rename_paths(path) {
+                if !entry.paths.contains(&path) {
+                    entry.paths.push(path);
+                }
+            }
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+/// Splits numstat's rename notation (`src/{a => b}/c.rs`, `old => new`) into
+/// the old and new path.
+fn rename_paths(path: &str) -> Vec<String> {
+    if let (Some(open), Some(close)) = (path.find('{'), path.find('}')) {
+        if let Some((old, new)) = path[open + 1..close].split_once(" => ") {
+            let (prefix, suffix) = (&path[..open], &path[close + 1..]);
+            return [old, new]
+                .iter()
+                .map(|part| format!("{}{}{}", prefix, part, suffix).replace("//", "/"))
+                .collect();
+        }
+    }
+    match path.split_once(" => ") {
+        Some((old, new)) => vec![old.to_string(), new.to_string()],
+        None => vec![path.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let output = "\x1eaaaa\x1fAda\x1f2024-03-01\x1fRename parser\n\n\
+                      2\t1\tsrc/{old => new}/parse.rs\n\
+                      \x1ebbbb\x1fBob\x1f2024-02-01\x1fAdd logo\n\n\
+                      -\t-\tlogo.png\n\
+                      4\t0\tREADME.md\n";
+        let entries = parse_log(output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].commit, "aaaa");
+        assert_eq!(entries[0].subject, "Rename parser");
+        assert_eq!((entries[0].added, entries[0].deleted), (2, 1));
+        assert_eq!(
+            entries[0].paths,
+            vec!["src/old/parse.rs", "src/new/parse.rs"]
+        );
+        assert_eq!((entries[1].added, entries[1].deleted), (4, 0));
+        assert_eq!(entries[1].paths, vec!["logo.png", "README.md"]);
+    }
+
+    #[test]
+    fn test_rename_paths() {
+        assert_eq!(rename_paths("a.rs => b.rs"), vec!["a.rs", "b.rs"]);
+        assert_eq!(
+            rename_paths("src/{ => sub}/x.rs"),
+            vec!["src/x.rs", "src/sub/x.rs"]
+        );
+        assert_eq!(rename_paths("plain.rs"), vec!["plain.rs"]);
+    }
+}
@@ -0,0 +1,275 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IgnoreTarget {
+    Root,    // <repo>/.gitignore
+    Local,   // .gitignore next to the selected paths
+    Exclude, // .git/info/exclude (not shared)
+    Global,  // core.excludesFile
+}
+
+impl IgnoreTarget {
+    pub fn next(&self) -> Self {
+        match self {
+            IgnoreTarget::Root => IgnoreTarget::Local,
+            IgnoreTarget::Local => IgnoreTarget::Exclude,
+            IgnoreTarget::Exclude => IgnoreTarget::Global,
+            IgnoreTarget::Global => IgnoreTarget::Root,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IgnoreTarget::Root => ".gitignore",
+            IgnoreTarget::Local => "local .gitignore",
+            IgnoreTarget::Exclude => ".git/info/exclude",
+            IgnoreTarget::Global => "global excludes",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IgnoreCandidate {
+    pub label: &'static str,
+    pub patterns: Vec<String>,
+}
+
+/// Directory (relative to the repo root, with trailing slash) shared by all paths,
+/// or `None` if they live in different directories.
+pub fn common_parent(paths: &[String]) -> Option<String> {
+    let mut parents = paths.iter().map(|p| parent_dir(p));
+    let first = parents.next()?;
+    if parents.all(|p| p == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+fn parent_dir(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(idx) => trimmed[..=idx].to_string(),
+        None => String::new(),
+    }
+}
+
+/// Builds the candidate patterns for `paths`.
+///
+/// `base` is the directory the ignore file lives in (`""` for the repo root).
+/// `None` means the file is not tied to this repository (global excludes), so
+/// only unanchored patterns are offered.
+pub fn candidate_patterns(paths: &[String], base: Option<&str>) -> Vec<IgnoreCandidate> {
+    let mut exact = Vec::new();
+    let mut dirs = Vec::new();
+    let mut exts = Vec::new();
+    let mut names = Vec::new();
+
+    for path in paths {
+        let is_dir = path.ends_with('/');
+        let trimmed = path.trim_end_matches('/');
+        let name = trimmed.rsplit('/').next().unwrap_or(trimmed);
+        let dir_suffix = if is_dir { "/" } else { "" };
+
+        if let Some(base) = base {
+            if let Some(rel) = trimmed.strip_prefix(base) {
+                push_unique(&mut exact, format!("/{}{}", rel, dir_suffix));
+
+                let parent = parent_dir(rel);
+                if !parent.is_empty() {
+                    push_unique(&mut dirs, format!("/{}", parent));
+                }
+            }
+        }
+
+        if !is_dir {
+            if let Some(ext) = Path::new(name).extension().and_then(|e| e.to_str()) {
+                push_unique(&mut exts, format!("*.{}", ext));
+            }
+        }
+
+        push_unique(&mut names, format!("{}{}", name, dir_suffix));
+    }
+
+    [
+        ("Exact path", exact),
+        ("Directory", dirs),
+        ("Extension", exts),
+        ("Name anywhere", names),
+    ]
+    .into_iter()
+    .filter(|(_, patterns)| !patterns.is_empty())
+    .map(|(label, patterns)| IgnoreCandidate { label, patterns })
+    .collect()
+}
+
+fn push_unique(list: &mut Vec<String>, item: String) {
+    if !list.contains(&item) {
+        list.push(item);
+    }
+}
+
+/// Rewrites a pattern from an ignore file in `base` so it can be passed to
+/// `git ls-files --exclude`, which always matches relative to the repo root.
+fn to_root_pattern(pattern: &str, base: &str) -> String {
+    if base.is_empty() {
+        pattern.to_string()
+    } else if let Some(anchored) = pattern.strip_prefix('/') {
+        format!("/{}{}", base, anchored)
+    } else {
+        format!("/{}**/{}", base, pattern)
+    }
+}
+
+pub fn get_toplevel() -> Result<PathBuf> {
+    super::rev_parse(&["--show-toplevel"])
+}
+
+pub fn target_file(target: IgnoreTarget, local_base: &str) -> Result<PathBuf> {
+    match target {
+        IgnoreTarget::Root => Ok(get_toplevel()?.join(".gitignore")),
+        IgnoreTarget::Local => Ok(get_toplevel()?.join(local_base).join(".gitignore")),
+        IgnoreTarget::Exclude => super::rev_parse(&["--git-path", "info/exclude"]),
+        IgnoreTarget::Global => {
+            if let Some(path) = super::get_config("core.excludesFile") {
+                return Ok(expand_home(&path));
+            }
+            let config_home = std::env::var("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|_| std::env::var("HOME").map(|h| PathBuf::from(h).join(".config")))
+                .context("Could not determine global excludes file")?;
+            Ok(config_home.join("git").join("ignore"))
+        }
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Ok(home) = std::env::var("HOME") {
+            return PathBuf::from(home).join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
+
+pub fn append_patterns(file: &Path, patterns: &[String]) -> Result<()> {
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let existing = std::fs::read_to_string(file).unwrap_or_default();
+    let mut out = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)
+        .with_context(|| format!("Failed to open {}", file.display()))?;
+
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        writeln!(out)?;
+    }
+    for pattern in patterns {
+        writeln!(out, "{}", pattern)?;
+    }
+    Ok(())
+}
+
+/// Lists the currently untracked files that `patterns` (as written into an
+/// ignore file in `base`) would hide.
+pub fn preview(patterns: &[String], base: Option<&str>) -> Result<Vec<String>> {
+    let toplevel = get_toplevel()?;
+    let base = base.unwrap_or("");
+
+    let untracked: HashSet<String> = ls_files(&toplevel, &["--exclude-standard".to_string()])?
+        .into_iter()
+        .collect();
+
+    let excludes: Vec<String> = patterns
+        .iter()
+        .map(|p| format!("--exclude={}", to_root_pattern(p, base)))
+        .collect();
+    let mut args = vec!["--ignored".to_string()];
+    args.extend(excludes);
+
+    Ok(ls_files(&toplevel, &args)?
+        .into_iter()
+        .filter(|p| untracked.contains(p))
+        .collect())
+}
+
+fn ls_files(toplevel: &Path, args: &[String]) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(toplevel)
+        .args(["ls-files", "--others"])
+        .args(args)
+        .output()
+        .context("Failed to execute git ls-files")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git ls-files failed: {}", err);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|s| s.to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_root() {
+        let paths = vec!["src/gen/foo.pb.rs".to_string()];
+        let candidates = candidate_patterns(&paths, Some(""));
+        assert_eq!(candidates.len(), 4);
+        assert_eq!(candidates[0].patterns, vec!["/src/gen/foo.pb.rs"]);
+        assert_eq!(candidates[1].patterns, vec!["/src/gen/"]);
+        assert_eq!(candidates[2].patterns, vec!["*.rs"]);
+        assert_eq!(candidates[3].patterns, vec!["foo.pb.rs"]);
+    }
+
+    #[test]
+    fn test_candidates_local_and_global() {
+        let paths = vec!["src/gen/a.log".to_string(), "src/gen/b.log".to_string()];
+        let local = candidate_patterns(&paths, Some("src/gen/"));
+        assert_eq!(local[0].patterns, vec!["/a.log", "/b.log"]);
+        // The parent is the base itself, so no directory candidate
+        assert_eq!(local[1].label, "Extension");
+        assert_eq!(local[1].patterns, vec!["*.log"]);
+
+        let global = candidate_patterns(&paths, None);
+        assert!(global
+            .iter()
+            .all(|c| c.patterns.iter().all(|p| !p.starts_with('/'))));
+    }
+
+    #[test]
+    fn test_candidates_directory() {
+        let paths = vec!["build/".to_string()];
+        let candidates = candidate_patterns(&paths, Some(""));
+        assert_eq!(candidates[0].patterns, vec!["/build/"]);
+        assert_eq!(candidates[1].label, "Name anywhere");
+        assert_eq!(candidates[1].patterns, vec!["build/"]);
+    }
+
+    #[test]
+    fn test_common_parent() {
+        let same = vec!["a/b/c.txt".to_string(), "a/b/d.txt".to_string()];
+        assert_eq!(common_parent(&same), Some("a/b/".to_string()));
+        let mixed = vec!["a/c.txt".to_string(), "d.txt".to_string()];
+        assert_eq!(common_parent(&mixed), None);
+    }
+
+    #[test]
+    fn test_to_root_pattern() {
+        assert_eq!(to_root_pattern("*.log", ""), "*.log");
+        assert_eq!(to_root_pattern("/a.log", "src/"), "/src/a.log");
+        assert_eq!(to_root_pattern("*.log", "src/"), "/src/**/*.log");
+    }
+}
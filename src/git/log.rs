@@ -0,0 +1,301 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::process::Command;
+
+use super::DiffOptions;
+use crate::node;
+use crate::parser;
+
+/// Commits read from `git log` at a time.
+pub const LOG_PAGE: usize = 200;
+
+/// One commit of the log, with the refs pointing at it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub commit: String,
+    pub parents: Vec<String>,
+    pub refs: Vec<String>, // Full names, e.g. `HEAD -> refs/heads/main`, `tag: refs/tags/v1.0`
+    pub author: String,
+    pub date: String, // YYYY-MM-DD
+    pub subject: String,
+}
+
+impl LogEntry {
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(7)]
+    }
+
+    /// Case-insensitive match on the subject, author, hash or refs.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.subject.to_lowercase().contains(&query)
+            || self.author.to_lowercase().contains(&query)
+            || self.commit.starts_with(&query)
+            || self.refs.iter().any(|r| r.to_lowercase().contains(&query))
+    }
+}
+
+/// Up to `LOG_PAGE` commits of every branch, remote and tag, after skipping
+/// the first `skip`. Topological order keeps pages stable and lanes tidy.
+pub fn log_page(skip: usize) -> Result<Vec<LogEntry>> {
+    let output = Command::new("git")
+        .args(["log", "--topo-order", "--date=short", "--decorate=full"])
+        .args(["--branches", "--remotes", "--tags", "HEAD"])
+        .arg(format!("--skip={}", skip))
+        .arg(format!("--max-count={}", LOG_PAGE))
+        .arg("--format=%H%x1f%P%x1f%D%x1f%an%x1f%ad%x1f%s")
+        .output()
+        .context("Failed to execute git log")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git log failed: {}", err);
+    }
+    Ok(parse_log(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_log(output: &str) -> Vec<LogEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\x1f').collect();
+            let [commit, parents, refs, author, date, subject] = fields[..] else {
+                return None;
+            };
+            Some(LogEntry {
+                commit: commit.to_string(),
+                parents: parents.split_whitespace().map(str::to_string).collect(),
+                refs: refs
+                    .split(", ")
+                    .filter(|r| !r.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                author: author.to_string(),
+                date: date.to_string(),
+                subject: subject.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The files `commit` changed, as a tree with line stats. Merges are compared
+/// against their first parent, root commits against the empty tree.
+pub fn commit_tree(commit: &str, options: &DiffOptions) -> Result<Option<node::Node>> {
+    let output = Command::new("git")
+        .args([
+            "diff-tree",
+            "-r",
+            "--root",
+            "--no-commit-id",
+            "--diff-merges=first-parent",
+            "--name-status",
+        ])
+        .arg(commit)
+        .output()
+        .context("Failed to execute git diff-tree")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git diff-tree failed: {}", err);
+    }
+
+    // Shown as unstaged changes so the tree doesn't mark them staged
+    let lines: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(status, path)| format!(" {} {}", &status[..1], path))
+        .collect();
+    if lines.is_empty() {
+        return Ok(None);
+    }
+
+    let mut stats = HashMap::new();
+    super::collect_diff_stats(
+        &mut stats,
+        &[
+            "diff-tree",
+            "-r",
+            "--root",
+            "--no-commit-id",
+            "--diff-merges=first-parent",
+            "--numstat",
+            commit,
+        ],
+        options,
+    )?;
+    Ok(Some(parser::build_tree(
+        lines, &stats, false, false, false,
+    )?))
+}
+
+/// What one column of a graph row shows. Lanes take the even columns; odd
+/// columns are the gaps between them, crossed by links.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphCell {
+    Empty,
+    Lane,   // A branch passing by
+    Node,   // The commit itself
+    Dash,   // A link crossing to another lane
+    Branch, // A lane on the left the commit links into
+    Fork,   // A lane opening below for a merged parent
+    Join,   // A lane above ending in the commit
+}
+
+/// Lays out lanes one commit at a time, so pages can be added as they load.
+#[derive(Debug, Default)]
+pub struct Graph {
+    lanes: Vec<Option<String>>, // The commit each lane waits for
+}
+
+impl Graph {
+    pub fn row(&mut self, entry: &LogEntry) -> Vec<GraphCell> {
+        let before = self.lanes.clone();
+        let col = match self
+            .lanes
+            .iter()
+            .position(|l| l.as_ref() == Some(&entry.commit))
+        {
+            Some(col) => col,
+            None => self.free_lane(0, &[]),
+        };
+
+        let mut joins = Vec::new();
+        for (i, lane) in self.lanes.iter_mut().enumerate() {
+            if i != col && lane.as_ref() == Some(&entry.commit) {
+                *lane = None;
+                joins.push(i);
+            }
+        }
+        self.lanes[col] = None;
+
+        let mut links = joins.clone();
+        let mut forks = Vec::new();
+        for (n, parent) in entry.parents.iter().enumerate() {
+            let waiting = self.lanes.iter().position(|l| l.as_ref() == Some(parent));
+            if let Some(k) = waiting.filter(|&k| n == 0 && k > col) {
+                // Pull a lane on the right into this one rather than leave a gap
+                self.lanes[k] = None;
+                self.lanes[col] = Some(parent.clone());
+                links.push(k);
+                joins.push(k);
+            } else if let Some(k) = waiting {
+                links.push(k);
+            } else if n == 0 {
+                self.lanes[col] = Some(parent.clone());
+            } else {
+                let k = self.free_lane(col + 1, &joins);
+                self.lanes[k] = Some(parent.clone());
+                links.push(k);
+                forks.push(k);
+            }
+        }
+
+        let width = before.len().max(self.lanes.len()).max(col + 1);
+        let lo = links.iter().copied().chain([col]).min().unwrap_or(col);
+        let hi = links.iter().copied().chain([col]).max().unwrap_or(col);
+        let mut cells = Vec::with_capacity(width * 2);
+        for i in 0..width {
+            let active = before.get(i).is_some_and(|l| l.is_some())
+                || self.lanes.get(i).is_some_and(|l| l.is_some());
+            cells.push(if i == col {
+                GraphCell::Node
+            } else if joins.contains(&i) {
+                GraphCell::Join
+            } else if forks.contains(&i) {
+                GraphCell::Fork
+            } else if i < col && links.contains(&i) {
+                GraphCell::Branch
+            } else if active {
+                GraphCell::Lane
+            } else if lo < i && i < hi {
+                GraphCell::Dash
+            } else {
+                GraphCell::Empty
+            });
+            if i + 1 < width {
+                cells.push(if lo <= i && i < hi {
+                    GraphCell::Dash
+                } else {
+                    GraphCell::Empty
+                });
+            }
+        }
+
+        while self.lanes.last().is_some_and(|l| l.is_none()) {
+            self.lanes.pop();
+        }
+        cells
+    }
+
+    /// First empty lane from `from` on, not counting `taken`, adding one if
+    /// there is none.
+    fn free_lane(&mut self, from: usize, taken: &[usize]) -> usize {
+        let free =
+            (from..self.lanes.len()).find(|&i| self.lanes[i].is_none() && !taken.contains(&i));
+        free.unwrap_or_else(|| {
+            self.lanes.push(None);
+            self.lanes.len() - 1
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use GraphCell::*;
+
+    fn entry(commit: &str, parents: &[&str]) -> LogEntry {
+        LogEntry {
+            commit: commit.to_string(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            refs: Vec::new(),
+            author: String::new(),
+            date: String::new(),
+            subject: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_log() {
+        let output = "aaaa\x1fbbbb cccc\x1fHEAD -> refs/heads/main, tag: refs/tags/v1\x1fAda\x1f2024-03-01\x1fMerge\n\
+                      cccc\x1f\x1f\x1fBob\x1f2024-02-01\x1fRoot\n";
+        let entries = parse_log(output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].parents, vec!["bbbb", "cccc"]);
+        assert_eq!(
+            entries[0].refs,
+            vec!["HEAD -> refs/heads/main", "tag: refs/tags/v1"]
+        );
+        assert!(entries[1].parents.is_empty());
+        assert!(entries[1].refs.is_empty());
+        assert!(entries[0].matches("V1"));
+        assert!(entries[1].matches("cc"));
+        assert!(!entries[1].matches("merge"));
+    }
+
+    #[test]
+    fn test_graph_merge() {
+        // m merges b into a; both branch off r
+        let mut graph = Graph::default();
+        assert_eq!(graph.row(&entry("m", &["a", "b"])), vec![Node, Dash, Fork]);
+        assert_eq!(graph.row(&entry("a", &["r"])), vec![Node, Empty, Lane]);
+        assert_eq!(graph.row(&entry("b", &["r"])), vec![Branch, Dash, Node]);
+        assert_eq!(graph.row(&entry("r", &[])), vec![Node]);
+    }
+
+    #[test]
+    fn test_graph_branches_join() {
+        // Two branch tips x and y sharing the parent p
+        let mut graph = Graph::default();
+        assert_eq!(graph.row(&entry("x", &["p"])), vec![Node]);
+        assert_eq!(graph.row(&entry("y", &["p"])), vec![Branch, Dash, Node]);
+        assert_eq!(graph.row(&entry("p", &[])), vec![Node]);
+
+        // The left branch's parent q comes first, then both meet at p
+        let mut graph = Graph::default();
+        assert_eq!(graph.row(&entry("x", &["q"])), vec![Node]);
+        assert_eq!(graph.row(&entry("y", &["p"])), vec![Lane, Empty, Node]);
+        assert_eq!(graph.row(&entry("q", &["p"])), vec![Node, Dash, Join]);
+        assert_eq!(graph.row(&entry("p", &[])), vec![Node]);
+    }
+}
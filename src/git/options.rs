@@ -0,0 +1,161 @@
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Whitespace {
+    #[default]
+    Show,
+    IgnoreAll,    // -w
+    IgnoreChange, // -b
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Algorithm {
+    #[default]
+    Default, // Whatever diff.algorithm says
+    Myers,
+    Patience,
+    Histogram,
+}
+
+// Rename similarity thresholds cycled through, after git's default (50%)
+const RENAME_THRESHOLDS: [u8; 3] = [90, 70, 30];
+
+/// `git diff` options toggled from the diff view. They apply to every diff
+/// and numstat git-twig runs, so tree stats match the diff on screen.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DiffOptions {
+    pub whitespace: Whitespace,
+    pub context: Option<usize>, // -U<n>; None keeps diff.context
+    pub algorithm: Algorithm,
+    pub rename_threshold: Option<u8>, // -M<n>%; None keeps git's default
+}
+
+impl DiffOptions {
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        match self.whitespace {
+            Whitespace::Show => {}
+            Whitespace::IgnoreAll => args.push("-w".to_string()),
+            Whitespace::IgnoreChange => args.push("-b".to_string()),
+        }
+        if let Some(context) = self.context {
+            args.push(format!("-U{}", context));
+        }
+        match self.algorithm {
+            Algorithm::Default => {}
+            Algorithm::Myers => args.push("--diff-algorithm=myers".to_string()),
+            Algorithm::Patience => args.push("--diff-algorithm=patience".to_string()),
+            Algorithm::Histogram => args.push("--diff-algorithm=histogram".to_string()),
+        }
+        if let Some(threshold) = self.rename_threshold {
+            args.push(format!("-M{}%", threshold));
+        }
+        args
+    }
+
+    /// Short description of the non-default options for the title bar.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        match self.whitespace {
+            Whitespace::Show => {}
+            Whitespace::IgnoreAll => parts.push("-w".to_string()),
+            Whitespace::IgnoreChange => parts.push("-b".to_string()),
+        }
+        if let Some(context) = self.context {
+            parts.push(format!("-U{}", context));
+        }
+        match self.algorithm {
+            Algorithm::Default => {}
+            Algorithm::Myers => parts.push("myers".to_string()),
+            Algorithm::Patience => parts.push("patience".to_string()),
+            Algorithm::Histogram => parts.push("histogram".to_string()),
+        }
+        if let Some(threshold) = self.rename_threshold {
+            parts.push(format!("-M{}%", threshold));
+        }
+        parts.join(" ")
+    }
+
+    pub fn cycle_whitespace(&mut self) {
+        self.whitespace = match self.whitespace {
+            Whitespace::Show => Whitespace::IgnoreAll,
+            Whitespace::IgnoreAll => Whitespace::IgnoreChange,
+            Whitespace::IgnoreChange => Whitespace::Show,
+        };
+    }
+
+    pub fn cycle_algorithm(&mut self) {
+        self.algorithm = match self.algorithm {
+            Algorithm::Default => Algorithm::Myers,
+            Algorithm::Myers => Algorithm::Patience,
+            Algorithm::Patience => Algorithm::Histogram,
+            Algorithm::Histogram => Algorithm::Default,
+        };
+    }
+
+    pub fn cycle_rename_threshold(&mut self) {
+        self.rename_threshold = match self.rename_threshold {
+            None => Some(RENAME_THRESHOLDS[0]),
+            Some(current) => RENAME_THRESHOLDS
+                .iter()
+                .position(|&t| t == current)
+                .and_then(|i| RENAME_THRESHOLDS.get(i + 1))
+                .copied(),
+        };
+    }
+
+    /// Grows (or shrinks) the context by `delta` lines, starting from git's default of 3.
+    pub fn change_context(&mut self, delta: isize) {
+        let current = self.context.unwrap_or(3) as isize;
+        self.context = Some((current + delta).max(0) as usize);
+    }
+
+    /// Hunks of diffs that hide whitespace changes or have no context don't
+    /// apply back to the index, so patch mode is off limits for them.
+    pub fn supports_patch(&self) -> bool {
+        self.whitespace == Whitespace::Show && self.context != Some(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_options_add_no_args() {
+        let options = DiffOptions::default();
+        assert!(options.args().is_empty());
+        assert_eq!(options.summary(), "");
+        assert!(options.supports_patch());
+    }
+
+    #[test]
+    fn test_options_args_and_summary() {
+        let mut options = DiffOptions::default();
+        options.cycle_whitespace();
+        options.change_context(2);
+        options.cycle_algorithm();
+        options.cycle_algorithm();
+        options.cycle_rename_threshold();
+
+        assert_eq!(
+            options.args(),
+            vec!["-w", "-U5", "--diff-algorithm=patience", "-M90%"]
+        );
+        assert_eq!(options.summary(), "-w -U5 patience -M90%");
+        assert!(!options.supports_patch());
+    }
+
+    #[test]
+    fn test_context_and_renames_bounds() {
+        let mut options = DiffOptions::default();
+        options.change_context(-10);
+        assert_eq!(options.context, Some(0));
+        assert!(!options.supports_patch());
+
+        for _ in 0..RENAME_THRESHOLDS.len() {
+            options.cycle_rename_threshold();
+        }
+        assert_eq!(options.rename_threshold, Some(30));
+        options.cycle_rename_threshold();
+        assert_eq!(options.rename_threshold, None);
+    }
+}
@@ -1,90 +1,525 @@
 use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::process::Command;
 
 #[derive(Debug, Clone)]
 pub struct Hunk {
-    #[allow(dead_code)]
     pub header: String,
     pub content: String,
     pub display_start: usize, // Line index in the full diff display
     pub display_end: usize,
+    pub file: usize, // Index into the `DiffFile`s of the same diff
 }
 
-pub fn parse_diff(diff_content: &str) -> (Vec<String>, Vec<Hunk>) {
-    let mut headers = Vec::new();
-    let mut hunks = Vec::new();
+/// One file section of a (possibly multi-file) diff: everything from its
+/// `diff --git` line up to its first `@@`.
+#[derive(Debug, Clone)]
+pub struct DiffFile {
+    pub path: String,
+    pub headers: Vec<String>,
+    pub display_start: usize,
+}
 
-    let lines: Vec<&str> = diff_content.lines().collect();
-    let mut i = 0;
+/// Path of the file a `diff --git a/<path> b/<path>` line is about.
+pub fn path_from_diff_header(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("diff --git ")?;
+    match rest.rfind(" b/") {
+        Some(idx) => Some(&rest[idx + 3..]),
+        None => rest.split_whitespace().last(),
+    }
+}
+
+/// Splits a diff into its files and hunks. Lines that are neither `diff --git`
+/// nor part of a hunk are file headers (`index`, `---`, `+++`, mode changes...).
+pub fn parse_diff(diff_content: &str) -> (Vec<DiffFile>, Vec<Hunk>) {
+    let mut files: Vec<DiffFile> = Vec::new();
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut in_hunk = false;
+
+    for (i, line) in diff_content.lines().enumerate() {
+        if line.starts_with("diff ") {
+            files.push(DiffFile {
+                path: path_from_diff_header(line).unwrap_or("").to_string(),
+                headers: vec![line.to_string()],
+                display_start: i,
+            });
+            in_hunk = false;
+            continue;
+        }
+
+        // Headerless input (e.g. a bare hunk) still gets a file to hang on
+        if files.is_empty() {
+            files.push(DiffFile {
+                path: String::new(),
+                headers: Vec::new(),
+                display_start: i,
+            });
+        }
+        let file = files.len() - 1;
 
-    // 1. Parse File Headers (everything before the first @@)
-    while i < lines.len() {
-        let line = lines[i];
         if line.starts_with("@@") {
-            break;
+            hunks.push(Hunk {
+                header: line.to_string(),
+                content: format!("{}\n", line),
+                display_start: i,
+                display_end: i,
+                file,
+            });
+            in_hunk = true;
+        } else if in_hunk {
+            if let Some(hunk) = hunks.last_mut() {
+                hunk.content.push_str(line);
+                hunk.content.push('\n');
+                hunk.display_end = i;
+            }
+        } else {
+            files[file].headers.push(line.to_string());
         }
-        headers.push(line.to_string());
-        i += 1;
     }
 
-    // 2. Parse Hunks
-    while i < lines.len() {
-        let line = lines[i];
-        if line.starts_with("@@") {
-            let start = i;
-            let header = line.to_string(); // Keep colored header for display mapping? No, Hunk structure is generic.
-                                           // Actually, we use Hunk for display range.
-                                           // The content we run apply on must use the stripped versions!
-                                           // But if we strip everything, we lose color in the TUI if we render FROM hunks?
-                                           // No, TUI renders from `diff_content` (original).
-                                           // Hunk just stores indices.
-                                           // Wait, apply_patch uses `hunk.content`.
-                                           // So `hunk.content` should ideally be stripped?
-                                           // Yes, git apply failed likely because of colors too.
-
-            let mut content = String::new();
-            content.push_str(line);
-            content.push('\n');
+    (files, hunks)
+}
 
-            i += 1;
-            while i < lines.len() {
-                let inner_line = lines[i];
-                if inner_line.starts_with("@@") {
-                    break; // Next hunk
+/// Drops everything but the `diff --git` line of the files whose path is in
+/// `folded`, so their hunks disappear from display and navigation.
+pub fn fold_files(diff_content: &str, folded: &HashSet<String>) -> String {
+    let mut out = String::new();
+    let mut hidden = false;
+    for line in diff_content.lines() {
+        if let Some(path) = path_from_diff_header(line) {
+            hidden = folded.contains(path);
+        } else if hidden {
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Extra context lines (above, below) per hunk, keyed by file path and the
+/// position of the hunk within that file.
+pub type Expansions = HashMap<(String, usize), (usize, usize)>;
+
+/// Grows hunks by the context lines asked for in `expansions`, taken from the
+/// new side of each file as `read` returns it. An expansion stops at the
+/// neighbouring hunk, so hunks never overlap and the diff still applies.
+pub fn expand_context(
+    diff_content: &str,
+    expansions: &Expansions,
+    mut read: impl FnMut(&str) -> Option<Vec<String>>,
+) -> String {
+    if expansions.is_empty() {
+        return diff_content.to_string();
+    }
+    let (files, mut hunks) = parse_diff(diff_content);
+
+    for (file_idx, file) in files.iter().enumerate() {
+        let file_hunks: Vec<usize> = (0..hunks.len())
+            .filter(|&i| hunks[i].file == file_idx)
+            .collect();
+        let expanded =
+            (0..file_hunks.len()).any(|n| expansions.contains_key(&(file.path.clone(), n)));
+        if !expanded {
+            continue;
+        }
+        let Some(lines) = read(&file.path) else {
+            continue;
+        };
+
+        let mut prev_end = 1; // First new-side line after the previous hunk
+        for (n, &i) in file_hunks.iter().enumerate() {
+            let next_start = file_hunks
+                .get(n + 1)
+                .and_then(|&next| parse_hunk_header(&hunks[next].header))
+                .map(|(_, _, start, count, _)| first_line(start, count))
+                .unwrap_or(lines.len() + 1);
+            let (above, below) = expansions
+                .get(&(file.path.clone(), n))
+                .copied()
+                .unwrap_or((0, 0));
+            if let Some((hunk, end)) =
+                expand_hunk(&hunks[i], above, below, prev_end, next_start, &lines)
+            {
+                hunks[i] = hunk;
+                prev_end = end;
+            }
+        }
+    }
+
+    render_diff(&files, &hunks)
+}
+
+// With a count of 0 a hunk's start is the line before the (empty) range
+fn first_line(start: usize, count: usize) -> usize {
+    if count == 0 {
+        start + 1
+    } else {
+        start
+    }
+}
+
+/// Adds up to `above`/`below` lines of context to `hunk`, staying within
+/// `prev_end..next_start` (new-side line numbers). Returns the hunk and the
+/// first new-side line after it.
+fn expand_hunk(
+    hunk: &Hunk,
+    above: usize,
+    below: usize,
+    prev_end: usize,
+    next_start: usize,
+    lines: &[String],
+) -> Option<(Hunk, usize)> {
+    let mut body = hunk.content.lines();
+    let (old_start, old_count, new_start, new_count, section) = parse_hunk_header(body.next()?)?;
+    let old_first = first_line(old_start, old_count);
+    let new_first = first_line(new_start, new_count);
+    let new_end = new_first + new_count;
+
+    let above = above.min(new_first.saturating_sub(prev_end));
+    // A "\ No newline at end of file" marker means the hunk already reaches the end
+    let below = if hunk.content.contains("\n\\") {
+        0
+    } else {
+        below
+            .min(next_start.saturating_sub(new_end))
+            .min((lines.len() + 1).saturating_sub(new_end))
+    };
+    if above == 0 && below == 0 {
+        return Some((hunk.clone(), new_end));
+    }
+    let (Some(before), Some(after)) = (
+        lines.get(new_first - 1 - above..new_first - 1),
+        lines.get(new_end - 1..new_end - 1 + below),
+    ) else {
+        return None;
+    };
+
+    // Both counts are non-zero now, so the starts are plain first lines
+    let header = format!(
+        "@@ -{},{} +{},{} @@{}",
+        old_first - above,
+        old_count + above + below,
+        new_first - above,
+        new_count + above + below,
+        section
+    );
+
+    let mut content = format!("{}\n", header);
+    for line in before {
+        content.push_str(&format!(" {}\n", line));
+    }
+    for line in body {
+        content.push_str(line);
+        content.push('\n');
+    }
+    for line in after {
+        content.push_str(&format!(" {}\n", line));
+    }
+
+    Some((
+        Hunk {
+            header,
+            content,
+            ..hunk.clone()
+        },
+        new_end + below,
+    ))
+}
+
+/// Parses `@@ -old_start,old_count +new_start,new_count @@ section` into its numbers
+/// and the trailing section text. Omitted counts default to 1.
+pub fn parse_hunk_header(header: &str) -> Option<(usize, usize, usize, usize, String)> {
+    let rest = header.strip_prefix("@@ -")?;
+    let (ranges, section) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+
+    let parse_range = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_count) = parse_range(old)?;
+    let (new_start, new_count) = parse_range(new)?;
+    Some((
+        old_start,
+        old_count,
+        new_start,
+        new_count,
+        section.to_string(),
+    ))
+}
+
+/// Narrows `hunk` down to the `selected` lines (offsets into `hunk.content`, where
+/// 0 is the `@@` header) and fixes up the `@@` counts.
+///
+/// Unselected additions are dropped and unselected deletions become context, which
+/// is what editing a hunk in `git add -p` amounts to. With `reverse` (the patch is
+/// applied with `--reverse`, e.g. to unstage) the roles of `+` and `-` swap.
+/// Returns `None` when no added or removed line is selected.
+pub fn select_lines(hunk: &Hunk, selected: &HashSet<usize>, reverse: bool) -> Option<Hunk> {
+    let mut lines = hunk.content.lines();
+    let (old_start, _, new_start, _, section) = parse_hunk_header(lines.next()?)?;
+
+    let (dropped, kept_as_context) = if reverse { ('-', '+') } else { ('+', '-') };
+
+    let mut body = Vec::new();
+    let mut old_count = 0;
+    let mut new_count = 0;
+    let mut any_change = false;
+    let mut last_dropped = false;
+
+    for (i, line) in lines.enumerate() {
+        let offset = i + 1;
+        let marker = line.chars().next().unwrap_or(' ');
+
+        if marker == '\\' {
+            // "\ No newline at end of file" belongs to the line before it
+            if !last_dropped {
+                body.push(line.to_string());
+            }
+            continue;
+        }
+
+        last_dropped = false;
+        if marker == '+' || marker == '-' {
+            if selected.contains(&offset) {
+                any_change = true;
+                body.push(line.to_string());
+                if marker == '-' {
+                    old_count += 1;
+                } else {
+                    new_count += 1;
                 }
-                content.push_str(inner_line);
-                content.push('\n');
-                i += 1;
+            } else if marker == dropped {
+                last_dropped = true;
+            } else if marker == kept_as_context {
+                body.push(format!(" {}", &line[1..]));
+                old_count += 1;
+                new_count += 1;
             }
+        } else {
+            body.push(line.to_string());
+            old_count += 1;
+            new_count += 1;
+        }
+    }
 
-            hunks.push(Hunk {
-                header,
-                content,
-                display_start: start,
-                display_end: i - 1,
-            });
+    if !any_change {
+        return None;
+    }
+
+    let header = format!(
+        "@@ -{},{} +{},{} @@{}",
+        old_start, old_count, new_start, new_count, section
+    );
+    let mut content = format!("{}\n", header);
+    for line in body {
+        content.push_str(&line);
+        content.push('\n');
+    }
+
+    Some(Hunk {
+        header,
+        content,
+        display_start: hunk.display_start,
+        display_end: hunk.display_end,
+        file: hunk.file,
+    })
+}
+
+/// Splits a hunk at the unchanged context runs between groups of changes, like `s`
+/// in `git add -p`. A context run is shared by both neighbours so every piece still
+/// applies on its own. A hunk with a single group of changes comes back as is.
+pub fn split_hunk(hunk: &Hunk) -> Vec<Hunk> {
+    let mut lines = hunk.content.lines();
+    let Some((old_start, _, new_start, _, section)) = lines.next().and_then(parse_hunk_header)
+    else {
+        return vec![hunk.clone()];
+    };
+    let body: Vec<&str> = lines.collect();
+
+    // A `\ No newline at end of file` marker belongs with the line before it
+    let mut changed = vec![false; body.len()];
+    for (i, line) in body.iter().enumerate() {
+        changed[i] = if line.starts_with('\\') {
+            i > 0 && changed[i - 1]
         } else {
-            i += 1;
+            line.starts_with('+') || line.starts_with('-')
+        };
+    }
+
+    // (first, last) body index of every run of changed lines
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for (i, _) in changed.iter().enumerate().filter(|(_, &c)| c) {
+        match groups.last_mut() {
+            Some((_, last)) if *last + 1 == i => *last = i,
+            _ => groups.push((i, i)),
         }
     }
 
-    (headers, hunks)
+    if groups.len() < 2 {
+        return vec![hunk.clone()];
+    }
+
+    let old_lines = |lines: &[&str]| {
+        lines
+            .iter()
+            .filter(|l| !l.starts_with('+') && !l.starts_with('\\'))
+            .count()
+    };
+    let new_lines = |lines: &[&str]| {
+        lines
+            .iter()
+            .filter(|l| !l.starts_with('-') && !l.starts_with('\\'))
+            .count()
+    };
+
+    let mut pieces = Vec::new();
+    for (g, &(first, last)) in groups.iter().enumerate() {
+        let from = if g == 0 { 0 } else { groups[g - 1].1 + 1 };
+        let to = if g == groups.len() - 1 {
+            body.len() - 1
+        } else {
+            groups[g + 1].0 - 1
+        };
+        debug_assert!(from <= first && last <= to);
+
+        let slice = &body[from..=to];
+        let header = format!(
+            "@@ -{},{} +{},{} @@{}",
+            old_start + old_lines(&body[..from]),
+            old_lines(slice),
+            new_start + new_lines(&body[..from]),
+            new_lines(slice),
+            section
+        );
+
+        let mut content = format!("{}\n", header);
+        for line in slice {
+            content.push_str(line);
+            content.push('\n');
+        }
+
+        pieces.push(Hunk {
+            header,
+            content,
+            display_start: hunk.display_start,
+            display_end: hunk.display_end,
+            file: hunk.file,
+        });
+    }
+    pieces
+}
+
+/// Re-assembles a diff from its files and hunks (e.g. after splitting one).
+pub fn render_diff(files: &[DiffFile], hunks: &[Hunk]) -> String {
+    let mut out = String::new();
+    for (idx, file) in files.iter().enumerate() {
+        for header in &file.headers {
+            out.push_str(header);
+            out.push('\n');
+        }
+        for hunk in hunks.iter().filter(|h| h.file == idx) {
+            out.push_str(&hunk.content);
+        }
+    }
+    out
 }
 
-pub fn apply_patch(headers: &[String], hunk: &Hunk, stage: bool) -> Result<()> {
+/// Builds a standalone patch (file headers + one hunk) that `git apply` accepts.
+pub fn build_patch(headers: &[String], hunk: &Hunk) -> String {
     let mut patch_content = String::new();
     for header in headers {
         patch_content.push_str(header);
         patch_content.push('\n');
     }
-    patch_content.push_str(&hunk.content); // hunk.content is already stripped now
+    patch_content.push_str(&hunk.content);
+    patch_content
+}
+
+/// Applies a single hunk with `git apply`. `cached` targets the index instead of
+/// the worktree; `reverse` undoes the hunk (unstage / discard).
+pub fn apply_patch(headers: &[String], hunk: &Hunk, cached: bool, reverse: bool) -> Result<()> {
+    apply_patch_text(&build_patch(headers, hunk), cached, reverse)
+}
+
+pub fn apply_patch_text(patch_content: &str, cached: bool, reverse: bool) -> Result<()> {
+    let mut args = Vec::new();
+    if cached {
+        args.push("--cached");
+    }
+    if reverse {
+        args.push("--reverse");
+    }
+    run_apply(patch_content, &args)
+}
+
+/// Rewrites the line counts of every `@@` header to match the lines that follow,
+/// so hand-edited hunks don't need to be counted by the user.
+pub fn recount(patch_content: &str) -> String {
+    let lines: Vec<&str> = patch_content.lines().collect();
+    let mut out = String::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let Some((old_start, _, new_start, _, section)) = parse_hunk_header(line) else {
+            out.push_str(line);
+            out.push('\n');
+            i += 1;
+            continue;
+        };
+
+        let body_end = lines[i + 1..]
+            .iter()
+            .position(|l| l.starts_with("@@") || l.starts_with("diff "))
+            .map(|p| i + 1 + p)
+            .unwrap_or(lines.len());
+        let body = &lines[i + 1..body_end];
+
+        let old_count = body
+            .iter()
+            .filter(|l| !l.starts_with('+') && !l.starts_with('\\'))
+            .count();
+        let new_count = body
+            .iter()
+            .filter(|l| !l.starts_with('-') && !l.starts_with('\\'))
+            .count();
+
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@{}\n",
+            old_start, old_count, new_start, new_count, section
+        ));
+        for l in body {
+            out.push_str(l);
+            out.push('\n');
+        }
+        i = body_end;
+    }
+    out
+}
+
+/// Applies a hand-edited patch to the index, checking it first so a broken
+/// edit leaves the index untouched.
+pub fn apply_edited_patch(patch_content: &str, reverse: bool) -> Result<()> {
+    let mut args = vec!["--cached"];
+    if reverse {
+        args.push("--reverse");
+    }
 
+    let mut check_args = args.clone();
+    check_args.push("--check");
+    run_apply(patch_content, &check_args)?;
+    run_apply(patch_content, &args)
+}
+
+fn run_apply(patch_content: &str, args: &[&str]) -> Result<()> {
     let mut cmd = Command::new("git");
     cmd.arg("apply");
-    if stage {
-        cmd.arg("--cached");
-    }
+    cmd.args(args);
     cmd.arg("-"); // Read from stdin
 
     let mut child = cmd
@@ -106,3 +541,207 @@ pub fn apply_patch(headers: &[String], hunk: &Hunk, stage: bool) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/f.txt b/f.txt\nindex 1..2 100644\n--- a/f.txt\n+++ b/f.txt\n@@ -1,2 +1,2 @@\n-a\n+A\n b\n@@ -10,1 +10,2 @@\n x\n+y\n";
+
+    #[test]
+    fn test_parse_diff_hunks() {
+        let (files, hunks) = parse_diff(DIFF);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "f.txt");
+        assert_eq!(files[0].headers.len(), 4);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].display_start, hunks[0].display_end), (4, 7));
+        assert_eq!((hunks[1].display_start, hunks[1].display_end), (8, 10));
+    }
+
+    #[test]
+    fn test_expand_context() {
+        let file: Vec<String> = (1..=12).map(|n| format!("l{}", n)).collect();
+        let diff =
+            "diff --git a/f b/f\n@@ -5,2 +5,2 @@\n-l5\n+L5\n l6\n@@ -10,1 +10,1 @@\n-l10\n+L10\n";
+        let mut expansions = Expansions::new();
+        expansions.insert(("f".to_string(), 0), (2, 5));
+        expansions.insert(("f".to_string(), 1), (5, 10));
+
+        let out = expand_context(diff, &expansions, |path| {
+            assert_eq!(path, "f");
+            Some(file.clone())
+        });
+        let (_, hunks) = parse_diff(&out);
+        // The first hunk grows up to the second, which then has nothing left above
+        assert_eq!(hunks[0].header, "@@ -3,7 +3,7 @@");
+        assert!(hunks[0].content.contains(" l3\n l4\n-l5\n"));
+        assert!(hunks[0].content.ends_with(" l6\n l7\n l8\n l9\n"));
+        assert_eq!(hunks[1].header, "@@ -10,3 +10,3 @@");
+        assert!(hunks[1].content.ends_with("+L10\n l11\n l12\n"));
+    }
+
+    #[test]
+    fn test_expand_context_pure_insertion() {
+        let file: Vec<String> = ["a", "new", "b"].iter().map(|s| s.to_string()).collect();
+        let diff = "diff --git a/f b/f\n@@ -1,0 +2,1 @@\n+new\n";
+        let mut expansions = Expansions::new();
+        expansions.insert(("f".to_string(), 0), (3, 3));
+
+        let out = expand_context(diff, &expansions, |_| Some(file.clone()));
+        assert!(out.ends_with("@@ -1,2 +1,3 @@\n a\n+new\n b\n"));
+    }
+
+    #[test]
+    fn test_build_patch() {
+        let (files, hunks) = parse_diff(DIFF);
+        let patch = build_patch(&files[0].headers, &hunks[1]);
+        assert!(patch.starts_with("diff --git a/f.txt b/f.txt\n"));
+        assert!(patch.ends_with("@@ -10,1 +10,2 @@\n x\n+y\n"));
+        assert!(!patch.contains("+A"));
+    }
+
+    #[test]
+    fn test_split_hunk() {
+        let h = hunk("@@ -1,7 +1,7 @@ fn f\n a\n-b\n+B\n c\n d\n-e\n+E\n f\n g\n");
+        let pieces = split_hunk(&h);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(
+            pieces[0].content,
+            "@@ -1,4 +1,4 @@ fn f\n a\n-b\n+B\n c\n d\n"
+        );
+        assert_eq!(
+            pieces[1].content,
+            "@@ -3,5 +3,5 @@ fn f\n c\n d\n-e\n+E\n f\n g\n"
+        );
+    }
+
+    #[test]
+    fn test_split_hunk_no_newline_marker() {
+        // Trailing context without a newline stays with the last change
+        let h =
+            hunk("@@ -1,7 +1,7 @@\n a\n-b\n+B\n c\n d\n-e\n+E\n f\n\\ No newline at end of file\n");
+        let pieces = split_hunk(&h);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(
+            pieces[1].content,
+            "@@ -3,4 +3,4 @@\n c\n d\n-e\n+E\n f\n\\ No newline at end of file\n"
+        );
+
+        // A marker after a removed line keeps that change in one piece
+        let h = hunk("@@ -1,5 +1,5 @@\n-a\n+A\n b\n c\n-d\n\\ No newline at end of file\n+D\n");
+        let pieces = split_hunk(&h);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(
+            pieces[1].content,
+            "@@ -2,3 +2,3 @@\n b\n c\n-d\n\\ No newline at end of file\n+D\n"
+        );
+    }
+
+    #[test]
+    fn test_split_hunk_single_group() {
+        let h = hunk("@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n");
+        let pieces = split_hunk(&h);
+        assert_eq!(pieces.len(), 1);
+        assert_eq!(pieces[0].content, h.content);
+    }
+
+    #[test]
+    fn test_recount() {
+        let edited = "--- a/f\n+++ b/f\n@@ -1,9 +1,9 @@ fn f\n a\n-b\n+B\n+C\n c\n";
+        assert_eq!(
+            recount(edited),
+            "--- a/f\n+++ b/f\n@@ -1,3 +1,4 @@ fn f\n a\n-b\n+B\n+C\n c\n"
+        );
+    }
+
+    #[test]
+    fn test_render_diff_roundtrip() {
+        let (files, hunks) = parse_diff(DIFF);
+        assert_eq!(render_diff(&files, &hunks), DIFF);
+    }
+
+    #[test]
+    fn test_parse_multi_file_diff() {
+        let second = "diff --git a/g.txt b/g.txt\nnew file mode 100644\n--- /dev/null\n+++ b/g.txt\n@@ -0,0 +1 @@\n+g\n";
+        let diff = format!("{}{}", DIFF, second);
+        let (files, hunks) = parse_diff(&diff);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].path, "g.txt");
+        assert_eq!(files[1].display_start, 11);
+        assert_eq!(hunks.len(), 3);
+        assert_eq!(hunks[1].display_end, 10);
+        assert_eq!(hunks[2].file, 1);
+        assert!(build_patch(&files[1].headers, &hunks[2]).starts_with("diff --git a/g.txt"));
+        assert_eq!(render_diff(&files, &hunks), diff);
+    }
+
+    #[test]
+    fn test_fold_files() {
+        let second = "diff --git a/g.txt b/g.txt\n--- a/g.txt\n+++ b/g.txt\n@@ -1 +1 @@\n-g\n+G\n";
+        let diff = format!("{}{}", DIFF, second);
+        let folded: HashSet<String> = ["f.txt".to_string()].into_iter().collect();
+
+        let (files, hunks) = parse_diff(&fold_files(&diff, &folded));
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].headers, vec!["diff --git a/f.txt b/f.txt"]);
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].file, 1);
+    }
+
+    #[test]
+    fn test_parse_hunk_header() {
+        assert_eq!(
+            parse_hunk_header("@@ -1,2 +1,3 @@ fn main() {"),
+            Some((1, 2, 1, 3, " fn main() {".to_string()))
+        );
+        assert_eq!(
+            parse_hunk_header("@@ -5 +5,0 @@"),
+            Some((5, 1, 5, 0, String::new()))
+        );
+        assert_eq!(parse_hunk_header("not a header"), None);
+    }
+
+    fn hunk(content: &str) -> Hunk {
+        Hunk {
+            header: content.lines().next().unwrap().to_string(),
+            content: content.to_string(),
+            display_start: 0,
+            display_end: content.lines().count() - 1,
+            file: 0,
+        }
+    }
+
+    #[test]
+    fn test_select_lines_stage() {
+        let h = hunk("@@ -1,3 +1,3 @@\n a\n-b\n-c\n+B\n+C\n d\n");
+        // Stage only "-b" and "+B"
+        let selected: HashSet<usize> = [2, 4].into_iter().collect();
+        let narrowed = select_lines(&h, &selected, false).unwrap();
+        assert_eq!(narrowed.content, "@@ -1,4 +1,4 @@\n a\n-b\n c\n+B\n d\n");
+    }
+
+    #[test]
+    fn test_select_lines_unstage() {
+        let h = hunk("@@ -1,2 +1,3 @@\n a\n-b\n+B\n+x\n");
+        // Unstage only "+x": the unselected "-b" is dropped, "+B" becomes context
+        let selected: HashSet<usize> = [4].into_iter().collect();
+        let narrowed = select_lines(&h, &selected, true).unwrap();
+        assert_eq!(narrowed.content, "@@ -1,2 +1,3 @@\n a\n B\n+x\n");
+    }
+
+    #[test]
+    fn test_select_lines_nothing_selected() {
+        let h = hunk("@@ -1,1 +1,1 @@\n-a\n+b\n");
+        assert!(select_lines(&h, &HashSet::new(), false).is_none());
+    }
+
+    #[test]
+    fn test_select_lines_no_newline_marker() {
+        let h = hunk("@@ -1,1 +1,1 @@\n-a\n+b\n\\ No newline at end of file\n");
+        // The marker goes away together with the dropped "+b"
+        let selected: HashSet<usize> = [1].into_iter().collect();
+        let narrowed = select_lines(&h, &selected, false).unwrap();
+        assert_eq!(narrowed.content, "@@ -1,1 +1,0 @@\n-a\n");
+    }
+}
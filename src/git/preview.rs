@@ -0,0 +1,202 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+
+/// Untracked files larger than this are shown truncated instead of diffed.
+pub const PREVIEW_LIMIT: u64 = 256 * 1024;
+const PREVIEW_LINES: usize = 2_000;
+/// How many bytes are checked for a NUL byte, like git's own binary check.
+const BINARY_SNIFF: usize = 8000;
+const LARGEST_SHOWN: usize = 10;
+
+/// Whether the untracked entry at `path` is a directory, or a file too large
+/// or too binary to show as a new-file diff.
+pub fn needs_preview(path: &str) -> Result<bool> {
+    let meta = std::fs::metadata(path).with_context(|| format!("Failed to stat {}", path))?;
+    if meta.is_dir() || meta.len() > PREVIEW_LIMIT {
+        return Ok(true);
+    }
+    Ok(is_binary(&read_head(path, BINARY_SNIFF as u64)?))
+}
+
+/// Whether every change under directory `path` is an untracked file.
+pub fn is_untracked_dir(path: &str) -> Result<bool> {
+    if !Path::new(path).is_dir() {
+        return Ok(false);
+    }
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "-u", "--", path])
+        .output()
+        .context("Failed to execute git status")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git status failed: {}", err);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(!stdout.is_empty() && stdout.lines().all(|l| l.starts_with("??")))
+}
+
+/// Text shown for an untracked file or directory: the start of a text file,
+/// a note for a binary one, or a summary of a directory's contents.
+pub fn preview_untracked(path: &str) -> Result<String> {
+    if Path::new(path).is_dir() {
+        let mut files = Vec::new();
+        for file in super::get_untracked_files(&[path.to_string()])? {
+            let size = std::fs::metadata(&file).map(|m| m.len()).unwrap_or(0);
+            files.push((file, size));
+        }
+        return Ok(summarize_dir(path, files));
+    }
+
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to stat {}", path))?
+        .len();
+    let head = read_head(path, PREVIEW_LIMIT)?;
+    if is_binary(&head[..head.len().min(BINARY_SNIFF)]) {
+        return Ok(format!(
+            "Untracked binary file {} ({})\n\nContents not shown.",
+            path,
+            format_size(size)
+        ));
+    }
+
+    let mut text = format!("Untracked file {} ({})\n\n", path, format_size(size));
+    let (shown, truncated) = first_lines(&head, size > PREVIEW_LIMIT);
+    text.push_str(&shown);
+    if truncated {
+        text.push_str(&format!(
+            "\n⋯ only the first {} lines are shown",
+            shown.lines().count()
+        ));
+    }
+    Ok(text)
+}
+
+/// Stands in for the new-file diff of an untracked file `needs_preview`
+/// rejects, in a diff of several files: its header and a one-line note.
+pub fn diff_stub(path: &str) -> Result<String> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to stat {}", path))?
+        .len();
+    Ok(format!(
+        "diff --git a/{0} b/{0}\nnew file mode 100644\n\
+         Untracked file {0} ({1}) not shown: open it on its own for a preview\n",
+        path,
+        format_size(size)
+    ))
+}
+
+/// At most `PREVIEW_LINES` full lines of `head`, and whether anything was
+/// left out. With `cut` the file goes on past `head`, so its last (partial)
+/// line is dropped.
+fn first_lines(head: &[u8], cut: bool) -> (String, bool) {
+    let text = String::from_utf8_lossy(head);
+    let mut lines: Vec<&str> = text.lines().collect();
+    let mut truncated = cut;
+    if cut && !text.ends_with('\n') {
+        lines.pop();
+    }
+    if lines.len() > PREVIEW_LINES {
+        lines.truncate(PREVIEW_LINES);
+        truncated = true;
+    }
+    (lines.join("\n"), truncated)
+}
+
+/// File count, total size and largest entries of an untracked directory.
+fn summarize_dir(path: &str, mut files: Vec<(String, u64)>) -> String {
+    let total: u64 = files.iter().map(|(_, size)| size).sum();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut text = format!(
+        "Untracked directory {}\n\n{} file{}, {}\n",
+        path.trim_end_matches('/'),
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+        format_size(total)
+    );
+    if !files.is_empty() {
+        text.push_str("\nLargest files:\n");
+        for (file, size) in files.iter().take(LARGEST_SHOWN) {
+            text.push_str(&format!("  {:>10}  {}\n", format_size(*size), file));
+        }
+        if files.len() > LARGEST_SHOWN {
+            text.push_str(&format!("  ⋯ and {} more\n", files.len() - LARGEST_SHOWN));
+        }
+    }
+    text
+}
+
+fn read_head(path: &str, limit: u64) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|f| f.take(limit).read_to_end(&mut bytes))
+        .with_context(|| format!("Failed to read {}", path))?;
+    Ok(bytes)
+}
+
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes.contains(&0)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"PNG\0\x01"));
+        assert!(!is_binary("plain text, ünïcode".as_bytes()));
+    }
+
+    #[test]
+    fn test_first_lines() {
+        assert_eq!(first_lines(b"a\nb\n", false), ("a\nb".to_string(), false));
+        assert_eq!(first_lines(b"a\nb\npart", true), ("a\nb".to_string(), true));
+
+        let long = "x\n".repeat(PREVIEW_LINES + 5);
+        let (shown, truncated) = first_lines(long.as_bytes(), false);
+        assert_eq!(shown.lines().count(), PREVIEW_LINES);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn test_summarize_dir() {
+        let mut files: Vec<(String, u64)> = (0..12)
+            .map(|i| (format!("out/f{:02}", i), i * 1024))
+            .collect();
+        files.push(("out/big.bin".to_string(), 5 * 1024 * 1024));
+        let text = summarize_dir("out/", files);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "Untracked directory out");
+        assert_eq!(lines[2], "13 files, 5.1 MiB");
+        assert_eq!(lines[5], "     5.0 MiB  out/big.bin");
+        assert_eq!(lines[6], "    11.0 KiB  out/f11");
+        assert_eq!(lines.last(), Some(&"  ⋯ and 3 more"));
+    }
+}
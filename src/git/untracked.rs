@@ -0,0 +1,291 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::process::Command;
+use std::time::SystemTime;
+
+use super::preview::format_size;
+use crate::node::{Node, NodeType};
+
+/// Untracked directories with more files than this are shown as one node.
+pub const DEFAULT_THRESHOLD: usize = 500;
+
+/// File count and total size of each summarized directory, by its path.
+pub type Summaries = HashMap<String, (usize, u64)>;
+
+/// How untracked directories are listed. Status runs with `-unormal`, and a
+/// directory git reports as a whole is split into its files unless it holds
+/// more than `threshold` of them and hasn't been expanded.
+#[derive(Debug, Clone, Default)]
+pub struct UntrackedSummary {
+    pub threshold: usize, // 0 lists every file, like `-uall`
+    pub expanded: HashSet<String>,
+    cache: HashMap<String, CachedDir>, // Summarized directories, by path
+}
+
+/// File count and size of a summarized directory, read when its entry was
+/// last modified at `modified`.
+#[derive(Debug, Clone)]
+struct CachedDir {
+    modified: SystemTime,
+    files: usize,
+    bytes: u64,
+}
+
+impl UntrackedSummary {
+    /// Reads `twig.untracked.threshold`.
+    pub fn from_config() -> Self {
+        let threshold = super::get_config("twig.untracked.threshold")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_THRESHOLD);
+        UntrackedSummary {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    pub fn status_arg(&self) -> &'static str {
+        if self.threshold == 0 {
+            "-uall"
+        } else {
+            "-unormal"
+        }
+    }
+
+    /// Count and size of summarized directory `dir`, if read since its entry
+    /// last changed.
+    fn cached(&self, dir: &str) -> Option<&CachedDir> {
+        let cached = self.cache.get(dir)?;
+        (modified(dir) == Some(cached.modified)).then_some(cached)
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[derive(Debug, PartialEq)]
+enum Entry {
+    File(String),
+    Dir(String, Vec<String>), // Summarized directory and the files in it
+}
+
+/// Rewrites the `?? dir/` lines of `git status -unormal` into one line per
+/// file, except for directories that stay summarized. A summarized directory
+/// whose entry hasn't changed since the last call isn't listed again.
+pub fn split_untracked_dirs(
+    lines: Vec<String>,
+    summary: &mut UntrackedSummary,
+) -> Result<(Vec<String>, Summaries)> {
+    let mut summaries = HashMap::new();
+    let mut unlisted = Vec::new();
+    for line in &lines {
+        let Some(dir) = line.strip_prefix("?? ").filter(|p| p.ends_with('/')) else {
+            continue;
+        };
+        match summary.cached(dir) {
+            Some(cached) if !summary.expanded.contains(dir) => {
+                summaries.insert(dir.to_string(), (cached.files, cached.bytes));
+            }
+            _ => unlisted.push(dir.to_string()),
+        }
+    }
+
+    let mut files = if unlisted.is_empty() {
+        HashMap::new()
+    } else {
+        group_by_dir(list_untracked(&unlisted)?, &unlisted)
+    };
+    let mut out = Vec::with_capacity(lines.len());
+    for line in lines {
+        let dir = match line.strip_prefix("?? ") {
+            Some(path) if path.ends_with('/') && !summaries.contains_key(path) => path,
+            _ => {
+                out.push(line);
+                continue;
+            }
+        };
+        // Nested repositories show up as directories without files
+        let Some(inside) = files.remove(dir) else {
+            out.push(line);
+            continue;
+        };
+        for entry in plan(dir, inside, summary) {
+            match entry {
+                Entry::File(path) => out.push(format!("?? {}", path)),
+                Entry::Dir(path, files) => {
+                    let count = files.len();
+                    let bytes = match summary.cached(&path) {
+                        Some(cached) if cached.files == count => cached.bytes,
+                        _ => files
+                            .iter()
+                            .filter_map(|f| std::fs::metadata(f).ok())
+                            .map(|m| m.len())
+                            .sum(),
+                    };
+                    if let Some(modified) = modified(&path) {
+                        let cached = CachedDir {
+                            modified,
+                            files: count,
+                            bytes,
+                        };
+                        summary.cache.insert(path.clone(), cached);
+                    }
+                    summaries.insert(path.clone(), (count, bytes));
+                    out.push(format!("?? {}", path));
+                }
+            }
+        }
+    }
+    summary.cache.retain(|dir, _| summaries.contains_key(dir));
+    Ok((out, summaries))
+}
+
+/// Sorts `files` into the directory of `dirs` (each ending in `/`) holding
+/// them.
+fn group_by_dir(files: Vec<String>, dirs: &[String]) -> HashMap<String, Vec<String>> {
+    let dirs: HashSet<&str> = dirs.iter().map(String::as_str).collect();
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for file in files {
+        let dir = file
+            .match_indices('/')
+            .map(|(i, _)| &file[..=i])
+            .find(|d| dirs.contains(d))
+            .map(str::to_string);
+        if let Some(dir) = dir {
+            groups.entry(dir).or_default().push(file);
+        }
+    }
+    groups
+}
+
+/// Decides which parts of untracked directory `dir` (ending in `/`) with
+/// `files` are summarized. An expanded directory shows its own files and
+/// looks at each subdirectory in turn.
+fn plan(dir: &str, files: Vec<String>, summary: &UntrackedSummary) -> Vec<Entry> {
+    if !summary.expanded.contains(dir) {
+        if summary.threshold > 0 && files.len() > summary.threshold {
+            return vec![Entry::Dir(dir.to_string(), files)];
+        }
+        return files.into_iter().map(Entry::File).collect();
+    }
+
+    let mut entries = Vec::new();
+    let mut subdirs: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for file in files {
+        match file[dir.len()..].split_once('/') {
+            Some((name, _)) => {
+                let sub = format!("{}{}/", dir, name);
+                subdirs.entry(sub).or_default().push(file);
+            }
+            None => entries.push(Entry::File(file)),
+        }
+    }
+    for (sub, files) in subdirs {
+        entries.extend(plan(&sub, files, summary));
+    }
+    entries
+}
+
+fn list_untracked(dirs: &[String]) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["ls-files", "--others", "--exclude-standard", "-z", "--"])
+        .args(dirs)
+        .output()
+        .context("Failed to execute git ls-files")?;
+
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Git ls-files failed: {}", err);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect())
+}
+
+/// Adds the file count and size to the names of summarized directories.
+pub fn label_summaries(node: &mut Node, summaries: &Summaries) {
+    match &mut node.node_type {
+        NodeType::Directory { children } => {
+            for child in children {
+                label_summaries(child, summaries);
+            }
+        }
+        NodeType::File { .. } => {
+            if let Some((files, bytes)) = summaries.get(&node.full_path) {
+                node.name = format!("{}/ [{} files, {}]", node.name, files, format_size(*bytes));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_plan_threshold() {
+        let summary = UntrackedSummary {
+            threshold: 2,
+            ..Default::default()
+        };
+        let small = files(&["out/a", "out/b"]);
+        assert_eq!(
+            plan("out/", small, &summary),
+            vec![Entry::File("out/a".into()), Entry::File("out/b".into())]
+        );
+
+        let big = files(&["out/a", "out/b", "out/c"]);
+        assert_eq!(
+            plan("out/", big.clone(), &summary),
+            vec![Entry::Dir("out/".into(), big)]
+        );
+    }
+
+    #[test]
+    fn test_group_by_dir() {
+        let groups = group_by_dir(
+            files(&["out/a", "out/sub/b", "cache/x", "other/y"]),
+            &files(&["out/", "cache/"]),
+        );
+        assert_eq!(groups["out/"], files(&["out/a", "out/sub/b"]));
+        assert_eq!(groups["cache/"], files(&["cache/x"]));
+        assert_eq!(groups.len(), 2);
+    }
+
+    #[test]
+    fn test_plan_expanded() {
+        let summary = UntrackedSummary {
+            threshold: 2,
+            expanded: HashSet::from(["node_modules/".to_string()]),
+            ..Default::default()
+        };
+        let all = files(&[
+            "node_modules/.bin",
+            "node_modules/big/a",
+            "node_modules/big/b",
+            "node_modules/big/c",
+            "node_modules/small/x",
+        ]);
+        assert_eq!(
+            plan("node_modules/", all, &summary),
+            vec![
+                Entry::File("node_modules/.bin".into()),
+                Entry::Dir(
+                    "node_modules/big/".into(),
+                    files(&[
+                        "node_modules/big/a",
+                        "node_modules/big/b",
+                        "node_modules/big/c"
+                    ])
+                ),
+                Entry::File("node_modules/small/x".into()),
+            ]
+        );
+    }
+}
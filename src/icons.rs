@@ -105,10 +105,87 @@ pub fn get_icon_color(name: &str, is_dir: bool) -> Option<Color> {
     }
 }
 
+/// Extension the syntax highlighter knows a file's language by. Only
+/// languages in syntect's default syntax set are listed; TOML, Swift,
+/// TypeScript, Kotlin and Dockerfiles have none, so they stay plain.
+pub fn get_syntax_extension(name: &str) -> Option<&'static str> {
+    match name {
+        "Makefile" => Some("Makefile"),
+        "package.json" => Some("json"),
+        _ => {
+            let ext = std::path::Path::new(name)
+                .extension()
+                .and_then(|s| s.to_str())?;
+            match ext {
+                "rs" => Some("rs"),
+                "md" => Some("md"),
+                "json" => Some("json"),
+                "yml" | "yaml" => Some("yaml"),
+                "sh" => Some("sh"),
+                "py" => Some("py"),
+                "js" => Some("js"),
+                "go" => Some("go"),
+                "rb" => Some("rb"),
+                "java" => Some("java"),
+                "c" | "h" => Some("c"),
+                "cpp" | "hpp" => Some("cpp"),
+                "css" => Some("css"),
+                "html" => Some("html"),
+                "sql" => Some("sql"),
+                _ => None,
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_syntax_extensions_resolve() {
+        let syntaxes = syntect::parsing::SyntaxSet::load_defaults_newlines();
+        let names = [
+            "Makefile",
+            "package.json",
+            "a.rs",
+            "a.md",
+            "a.json",
+            "a.yml",
+            "a.yaml",
+            "a.sh",
+            "a.py",
+            "a.js",
+            "a.go",
+            "a.rb",
+            "a.java",
+            "a.c",
+            "a.h",
+            "a.cpp",
+            "a.hpp",
+            "a.css",
+            "a.html",
+            "a.sql",
+            "Cargo.toml",
+            "a.toml",
+            "a.swift",
+            "a.ts",
+            "a.kt",
+            "Dockerfile",
+            ".env",
+        ];
+        for name in names {
+            if let Some(ext) = get_syntax_extension(name) {
+                assert!(
+                    syntaxes.find_syntax_by_extension(ext).is_some(),
+                    "{} maps to unknown syntax {}",
+                    name,
+                    ext
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_known_extensions() {
         assert_eq!(get_icon("file.rs", false), "");
@@ -131,6 +208,15 @@ mod tests {
         assert_eq!(get_icon("other", true), "");
     }
 
+    #[test]
+    fn test_syntax_extension() {
+        assert_eq!(get_syntax_extension("main.rs"), Some("rs"));
+        assert_eq!(get_syntax_extension("index.ts"), None);
+        assert_eq!(get_syntax_extension("Makefile"), Some("Makefile"));
+        assert_eq!(get_syntax_extension("photo.png"), None);
+        assert_eq!(get_syntax_extension("README"), None);
+    }
+
     #[test]
     fn test_unknown_file() {
         assert_eq!(get_icon("unknown.xyz", false), "");
@@ -43,6 +43,10 @@ struct Args {
     #[arg(long)]
     untracked_only: bool,
 
+    /// Include ignored files (dimmed)
+    #[arg(long)]
+    ignored: bool,
+
     /// Visual theme (ascii, unicode, nerd)
     #[arg(long, value_enum)]
     theme: Option<ThemeType>,
@@ -108,25 +112,37 @@ fn main() -> Result<()> {
     let theme = determine_theme(args.theme).with_simple_icons(args.simple_icons);
 
     if args.interactive {
-        return tui::run(indent, collapse, theme);
+        return tui::run(indent, collapse, theme, args.ignored);
     }
 
-    let result_node =
-        match git::build_tree_from_git(args.staged_only, args.modified_only, args.untracked_only) {
-            Ok(Some(node)) => node,
-            Ok(None) => {
-                if !args.open && !args.json && !args.yaml {
-                    if let Ok(header) = git::get_status_header() {
-                        print_context_header(&header);
-                    }
-                    println!("(working directory clean)");
-                } else if args.json || args.yaml {
-                    println!("{{}}");
+    let result_node = match git::build_tree_from_git(
+        args.staged_only,
+        args.modified_only,
+        args.untracked_only,
+        args.ignored,
+        &git::DiffOptions::default(),
+        // Opening files needs every one of them listed, and JSON/YAML output
+        // keeps plain paths for scripts
+        &mut if args.open || args.json || args.yaml {
+            git::UntrackedSummary::default()
+        } else {
+            git::UntrackedSummary::from_config()
+        },
+    ) {
+        Ok(Some(node)) => node,
+        Ok(None) => {
+            if !args.open && !args.json && !args.yaml {
+                if let Ok(header) = git::get_status_header() {
+                    print_context_header(&header);
                 }
-                return Ok(());
+                println!("(working directory clean)");
+            } else if args.json || args.yaml {
+                println!("{{}}");
             }
-            Err(e) => return Err(e),
-        };
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
 
     if args.json {
         println!("{}", serde_json::to_string_pretty(&result_node)?);
@@ -77,7 +77,9 @@ impl Node {
 
             NodeType::File { status, stats: _ } => {
                 let staged = status.contains('+');
-                let color_name = if staged {
+                let color_name = if status == "!!" {
+                    self.name.dimmed()
+                } else if staged {
                     self.name.green()
                 } else {
                     self.name.red()
@@ -330,6 +332,8 @@ impl Node {
             NodeType::File { status, .. } => {
                 if status.contains('+') {
                     '+'
+                } else if status == "!!" {
+                    '!'
                 } else if status.contains('?') {
                     '?'
                 } else {
@@ -23,6 +23,8 @@ pub fn parse_status_line(line: &str) -> Option<(String, String)> {
     // Determine status string
     let status = if x == '?' && y == '?' {
         "??".to_string()
+    } else if x == '!' && y == '!' {
+        "!!".to_string()
     } else if y == ' ' {
         format!("{}+", x)
     } else {
@@ -222,6 +224,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_status_ignored() {
+        assert_eq!(
+            parse_status_line("!! debug.log").unwrap(),
+            ("debug.log".to_string(), "!!".to_string())
+        );
+        assert_eq!(
+            parse_status_line("!! build/").unwrap(),
+            ("build/".to_string(), "!!".to_string())
+        );
+    }
+
     #[test]
     fn test_parse_status_modified_staged() {
         assert_eq!(
@@ -26,6 +26,9 @@ pub struct Theme {
     pub tree_branch: char,
     pub tree_end: char,
     pub tree_dash: char,
+    pub graph_node: char,
+    pub graph_fork: char, // A lane opening below a merge
+    pub graph_join: char, // A lane ending in a commit
     pub icon_dir: &'static str,
     pub icon_file: &'static str,
     pub diff_bar_plus: char,
@@ -58,6 +61,9 @@ impl Theme {
             tree_branch: '|',
             tree_end: '`',
             tree_dash: '-',
+            graph_node: '*',
+            graph_fork: '.',
+            graph_join: '\'',
             icon_dir: "",
             icon_file: "",
             diff_bar_plus: '+',
@@ -76,6 +82,9 @@ impl Theme {
             tree_branch: '├',
             tree_end: '└',
             tree_dash: '─',
+            graph_node: '●',
+            graph_fork: '┐',
+            graph_join: '┘',
             icon_dir: "",
             icon_file: "",
             diff_bar_plus: '◼',
@@ -94,6 +103,9 @@ impl Theme {
             tree_branch: '├',
             tree_end: '╰',
             tree_dash: '─',
+            graph_node: '●',
+            graph_fork: '╮',
+            graph_join: '╯',
             icon_dir: "",
             icon_file: "",
             diff_bar_plus: '◼',
@@ -112,6 +124,9 @@ impl Theme {
             tree_branch: '├',
             tree_end: '└',
             tree_dash: '─',
+            graph_node: '●',
+            graph_fork: '┐',
+            graph_join: '┘',
             icon_dir: " ",
             icon_file: " ",
             diff_bar_plus: '◼',
@@ -1,19 +1,34 @@
 use anyhow::Result;
 use ratatui::widgets::ListState;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use unicode_width::UnicodeWidthStr;
 
 use crate::config::KeyConfig;
-use crate::git::{self, Worktree};
+use crate::git::discard::WorktreeChange;
+use crate::git::{self, IgnoreCandidate, IgnoreTarget, Worktree};
 use crate::node::FlatNode;
 use crate::theme::{Theme, ThemeType};
+use crate::tui::blame_view::BlameView;
+use crate::tui::branch_modal::{BranchConfirm, BranchInput, BranchModal};
+use crate::tui::diff_model::{DiffModel, LARGE_DIFF_LINES};
+use crate::tui::diff_tool::DiffToolCache;
+use crate::tui::file_view::{FileSide, FileView};
+use crate::tui::highlight::HighlightCache;
 use crate::tui::history::{ActionHistory, StageAction};
+use crate::tui::history_view::HistoryView;
+use crate::tui::image_view::{self, ImageDiff};
+use crate::tui::log_view::LogView;
+use crate::tui::side_by_side;
+
+// Lines of context each expand above/below adds to a hunk
+const EXPAND_STEP: usize = 10;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FilterMode {
     All,
     Modified, // Hides untracked
     Staged,   // Shows only staged
+    Ignored,  // Shows everything plus ignored files
 }
 
 impl FilterMode {
@@ -21,7 +36,8 @@ impl FilterMode {
         match self {
             FilterMode::All => FilterMode::Modified,
             FilterMode::Modified => FilterMode::Staged,
-            FilterMode::Staged => FilterMode::All,
+            FilterMode::Staged => FilterMode::Ignored,
+            FilterMode::Ignored => FilterMode::All,
         }
     }
 
@@ -30,6 +46,7 @@ impl FilterMode {
             FilterMode::All => "All",
             FilterMode::Modified => "Modified",
             FilterMode::Staged => "Staged",
+            FilterMode::Ignored => "Ignored",
         }
     }
 }
@@ -68,10 +85,64 @@ impl Focus {
     }
 }
 
+/// How the diff view lays out old and new lines, persisted as `twig.diff.layout`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffLayout {
+    Unified,
+    SideBySide,
+}
+
+impl DiffLayout {
+    pub fn load() -> Self {
+        match git::get_config("twig.diff.layout").as_deref() {
+            Some("side-by-side") => DiffLayout::SideBySide,
+            _ => DiffLayout::Unified,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            DiffLayout::Unified => DiffLayout::SideBySide,
+            DiffLayout::SideBySide => DiffLayout::Unified,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiffLayout::Unified => "unified",
+            DiffLayout::SideBySide => "side-by-side",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ViewMode {
     Tree,
     Diff,
+    File,
+    Blame,
+    History,
+    Log,
+}
+
+/// What the diff view is showing, so it can be re-read after staging.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffTarget {
+    File(String),
+    Paths(Vec<String>), // Everything under a directory or visual selection
+    Review,             // Every changed file, like `git add -p` on the whole tree
+    Preview(String),    // Untracked directory, or file too large or binary to diff
+    Commit(String, Vec<String>), // `git show` of a commit, limited to some paths if any
+}
+
+/// A diff put aside while blame or history is open: what it showed, its
+/// sides, and the view it goes back to.
+pub type SavedDiff = (DiffTarget, git::DiffMode, ViewMode);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscardRequest {
+    Files(Vec<WorktreeChange>),
+    Hunk(usize),
 }
 
 pub struct App {
@@ -91,7 +162,29 @@ pub struct App {
     pub is_typing_search: bool,
     pub view_mode: ViewMode,
     pub diff_content: String,
-    pub diff_scroll: u16,
+    pub diff_scroll: usize,  // Raw diff line at the top of the view
+    pub diff_hscroll: usize, // Columns scrolled off the left of diff lines
+    pub diff_wrap: bool,
+    pub diff_text_width: usize, // Columns of diff text on screen, kept by the renderer
+    pub diff_height: usize,     // Rows of diff on screen, kept by the renderer
+    pub diff_target: Option<DiffTarget>,
+    pub diff_mode: git::DiffMode,
+    pub diff_options: git::DiffOptions,
+    pub untracked: git::UntrackedSummary,
+    pub diff_layout: DiffLayout,
+    pub word_regex: Option<regex::Regex>, // git's diff.wordRegex, if set and valid
+    pub syntax_highlight: bool,
+    pub diff_tool: Option<String>, // twig.diff.tool, e.g. "delta --paging=never"
+    pub diff_tool_cache: DiffToolCache,
+    pub highlight: HighlightCache,
+    pub diff_untracked: bool,
+    pub file_view: Option<FileView>,
+    pub image_diff: Option<ImageDiff>,
+    pub blame_view: Option<BlameView>,
+    pub history_view: Option<HistoryView>,
+    pub log_view: Option<LogView>,
+    pub viewed_commit: Option<git::log::LogEntry>, // Commit whose files the tree shows
+    pub diff_return: ViewMode,                     // Where closing the diff goes
     pub theme: Theme,
     pub theme_type: ThemeType,
     pub max_name_width: usize,
@@ -108,6 +201,7 @@ pub struct App {
     pub worktrees: Vec<Worktree>,
     pub worktree_state: ListState,
     pub show_worktrees: bool,
+    pub branch_modal: Option<BranchModal>,
     pub max_help_scroll: u16,
     pub diff_search_query: String,
     pub is_diff_search: bool,
@@ -117,13 +211,40 @@ pub struct App {
     pub commit_message: String,
     // Patch Mode
     pub patch_mode: bool,
-    pub diff_headers: Vec<String>,
+    pub diff_files: Vec<crate::git::DiffFile>,
+    pub diff_model: DiffModel,
+    pub diff_deferred: bool, // A large diff waiting for `L` to be shown
+    pub diff_folded: HashSet<String>, // Paths of files folded in a multi-file diff
+    pub diff_expanded: git::patch::Expansions, // Extra context shown around hunks
+    pub fold_unchanged: bool, // Collapse long runs of context lines
     pub diff_hunks: Vec<crate::git::Hunk>,
     pub selected_hunk_idx: Option<usize>,
+    // Line selection inside the selected hunk (display line indices)
+    pub line_mode: bool,
+    pub line_cursor: usize,
+    pub selected_lines: HashSet<usize>,
+    // Ignored files: path -> matching rule (source:line:pattern)
+    pub ignore_rules: HashMap<String, String>,
+    // Add to ignore dialog
+    pub show_ignore_dialog: bool,
+    pub ignore_paths: Vec<String>,
+    pub ignore_target: IgnoreTarget,
+    pub ignore_candidates: Vec<IgnoreCandidate>,
+    pub ignore_state: ListState,
+    pub ignore_preview: Vec<String>,
+    // Discard confirmation
+    pub pending_discard: Option<DiscardRequest>,
+    // One-line feedback (e.g. a rejected hunk edit), cleared on the next key
+    pub status_message: Option<String>,
 }
 
 impl App {
-    pub fn new(indent_size: usize, collapse: bool, theme: Theme) -> Result<Self> {
+    pub fn new(
+        indent_size: usize,
+        collapse: bool,
+        theme: Theme,
+        filter_mode: FilterMode,
+    ) -> Result<Self> {
         let mut app = App {
             indent_size,
             collapse,
@@ -135,13 +256,36 @@ impl App {
             unstaged_state: ListState::default(),
             unified_state: ListState::default(),
             layout: AppLayout::Unified,
-            filter_mode: FilterMode::All,
+            filter_mode,
             focus: Focus::Unstaged,
             search_query: String::new(),
             is_typing_search: false,
             view_mode: ViewMode::Tree,
             diff_content: String::new(),
             diff_scroll: 0,
+            diff_hscroll: 0,
+            diff_wrap: false,
+            diff_text_width: 80,
+            diff_height: 20,
+            diff_target: None,
+            diff_mode: git::DiffMode::Worktree,
+            diff_options: git::DiffOptions::default(),
+            untracked: git::UntrackedSummary::from_config(),
+            diff_layout: DiffLayout::load(),
+            word_regex: git::get_repo_config("diff.wordRegex")
+                .and_then(|re| regex::Regex::new(&re).ok()),
+            syntax_highlight: git::get_config("twig.diff.syntax").as_deref() != Some("false"),
+            highlight: HighlightCache::default(),
+            diff_tool: git::get_config("twig.diff.tool"),
+            diff_tool_cache: DiffToolCache::default(),
+            diff_untracked: false,
+            file_view: None,
+            image_diff: None,
+            blame_view: None,
+            history_view: None,
+            log_view: None,
+            viewed_commit: None,
+            diff_return: ViewMode::Tree,
             theme: theme.clone(),
             theme_type: ThemeType::Unicode, // Will be set by determine_theme usually
             max_name_width: 0,
@@ -158,6 +302,7 @@ impl App {
             worktrees: Vec::new(),
             worktree_state: ListState::default(),
             show_worktrees: false,
+            branch_modal: None,
             max_help_scroll: 0,
             diff_search_query: String::new(),
             is_diff_search: false,
@@ -166,9 +311,26 @@ impl App {
             show_commit_dialog: false,
             commit_message: String::new(),
             patch_mode: false,
-            diff_headers: Vec::new(),
+            diff_files: Vec::new(),
+            diff_model: DiffModel::default(),
+            diff_deferred: false,
+            diff_folded: HashSet::new(),
+            diff_expanded: git::patch::Expansions::new(),
+            fold_unchanged: false,
             diff_hunks: Vec::new(),
             selected_hunk_idx: None,
+            line_mode: false,
+            line_cursor: 0,
+            selected_lines: HashSet::new(),
+            ignore_rules: HashMap::new(),
+            show_ignore_dialog: false,
+            ignore_paths: Vec::new(),
+            ignore_target: IgnoreTarget::Root,
+            ignore_candidates: Vec::new(),
+            ignore_state: ListState::default(),
+            ignore_preview: Vec::new(),
+            pending_discard: None,
+            status_message: None,
         };
         app.refresh()?;
         Ok(app)
@@ -189,15 +351,45 @@ impl App {
     }
 
     pub fn refresh(&mut self) -> Result<()> {
+        if let Some(entry) = &self.viewed_commit {
+            let tree = git::log::commit_tree(&entry.commit, &self.diff_options)?;
+            self.unified_nodes = match tree {
+                Some(root) => root.flatten(
+                    self.indent_size,
+                    self.collapse,
+                    &self.theme,
+                    &self.collapsed_paths,
+                ),
+                None => Vec::new(),
+            };
+            self.max_name_width = self
+                .unified_nodes
+                .iter()
+                .map(|n| n.connector.width() + n.name.width())
+                .max()
+                .unwrap_or(0);
+            Self::adjust_selection(&self.unified_nodes, &mut self.unified_state, true);
+            self.ignore_rules.clear();
+            return Ok(());
+        }
+
         match self.layout {
             AppLayout::Unified | AppLayout::EasterEgg => {
-                let (staged, modified) = match self.filter_mode {
-                    FilterMode::All => (false, false),
-                    FilterMode::Modified => (false, true),
-                    FilterMode::Staged => (true, false),
+                let (staged, modified, ignored) = match self.filter_mode {
+                    FilterMode::All => (false, false, false),
+                    FilterMode::Modified => (false, true, false),
+                    FilterMode::Staged => (true, false, false),
+                    FilterMode::Ignored => (false, false, true),
                 };
 
-                let tree = git::build_tree_from_git(staged, modified, false)?;
+                let tree = git::build_tree_from_git(
+                    staged,
+                    modified,
+                    false,
+                    ignored,
+                    &self.diff_options,
+                    &mut self.untracked,
+                )?;
                 if let Some(root) = tree {
                     self.unified_nodes = root.flatten(
                         self.indent_size,
@@ -217,9 +409,28 @@ impl App {
                     .unwrap_or(0);
 
                 Self::adjust_selection(&self.unified_nodes, &mut self.unified_state, true);
+
+                self.ignore_rules = if ignored {
+                    let paths: Vec<String> = self
+                        .unified_nodes
+                        .iter()
+                        .filter(|n| n.raw_status == "!!")
+                        .map(|n| n.full_path.clone())
+                        .collect();
+                    git::get_ignore_rules(&paths).unwrap_or_default()
+                } else {
+                    HashMap::new()
+                };
             }
             AppLayout::Split => {
-                let staged_tree = git::build_tree_from_git(true, false, false)?;
+                let staged_tree = git::build_tree_from_git(
+                    true,
+                    false,
+                    false,
+                    false,
+                    &self.diff_options,
+                    &mut self.untracked,
+                )?;
                 if let Some(root) = staged_tree {
                     self.staged_nodes = root.flatten(
                         self.indent_size,
@@ -231,7 +442,14 @@ impl App {
                     self.staged_nodes = Vec::new();
                 }
 
-                let all_tree = git::build_tree_from_git(false, false, false)?;
+                let all_tree = git::build_tree_from_git(
+                    false,
+                    false,
+                    false,
+                    false,
+                    &self.diff_options,
+                    &mut self.untracked,
+                )?;
                 if let Some(root) = all_tree {
                     let all = root.flatten(
                         self.indent_size,
@@ -272,7 +490,14 @@ impl App {
                 );
             }
             AppLayout::Compact => {
-                let tree = git::build_tree_from_git(false, false, false)?;
+                let tree = git::build_tree_from_git(
+                    false,
+                    false,
+                    false,
+                    false,
+                    &self.diff_options,
+                    &mut self.untracked,
+                )?;
                 if let Some(root) = tree {
                     self.unified_nodes = root.flatten(
                         self.indent_size,
@@ -297,8 +522,12 @@ impl App {
 
         // Collect global stats
         let mut stats_map = std::collections::HashMap::new();
-        let _ = git::collect_diff_stats(&mut stats_map, &["diff", "--numstat"]);
-        let _ = git::collect_diff_stats(&mut stats_map, &["diff", "--cached", "--numstat"]);
+        let _ = git::collect_diff_stats(&mut stats_map, &["diff", "--numstat"], &self.diff_options);
+        let _ = git::collect_diff_stats(
+            &mut stats_map,
+            &["diff", "--cached", "--numstat"],
+            &self.diff_options,
+        );
 
         let mut total_added = 0;
         let mut total_deleted = 0;
@@ -361,6 +590,11 @@ impl App {
     }
 
     pub fn show_diff(&mut self) -> Result<()> {
+        if let Some(entry) = &self.viewed_commit {
+            let commit = entry.commit.clone();
+            return self.show_commit_diff(commit);
+        }
+
         let (nodes, state) = match self.layout {
             AppLayout::Unified | AppLayout::Compact | AppLayout::EasterEgg => {
                 (&self.unified_nodes, &mut self.unified_state)
@@ -373,27 +607,46 @@ impl App {
 
         let filtered = Self::filter_nodes(nodes, &self.search_query);
 
+        if self.is_visual_mode
+            || state
+                .selected()
+                .and_then(|i| filtered.get(i))
+                .is_some_and(|n| n.is_dir)
+        {
+            return self.show_combined_diff();
+        }
+
         if let Some(i) = state.selected() {
             if let Some(node) = filtered.get(i) {
-                if node.is_dir {
-                    return Ok(());
-                }
-
-                let is_staged = node.raw_status.contains('+');
+                let mode = if node.raw_status.contains('+') {
+                    git::DiffMode::Index
+                } else {
+                    git::DiffMode::Worktree
+                };
                 let is_untracked = node.raw_status == "??";
+                if is_untracked
+                    && !image_view::is_image(&node.full_path)
+                    && git::preview::needs_preview(&node.full_path).unwrap_or(false)
+                {
+                    let path = node.full_path.clone();
+                    return self.show_preview(path);
+                }
+                self.diff_target = Some(DiffTarget::File(node.full_path.clone()));
+                self.diff_folded.clear();
+                self.diff_expanded.clear();
+                self.diff_mode = mode;
+                self.diff_untracked = is_untracked;
 
-                match git::get_diff(&node.full_path, is_staged, is_untracked) {
+                match git::get_diff(&node.full_path, mode, is_untracked, &self.diff_options) {
                     Ok(content) => {
                         if content.is_empty() && !is_untracked {
-                            self.diff_content = "(No diff or binary file)".to_string();
+                            self.open_diff("(No diff or binary file)".to_string());
                         } else {
-                            self.diff_content = content;
+                            self.open_diff(content);
                         }
-                        self.view_mode = ViewMode::Diff;
-                        self.diff_scroll = 0;
                     }
                     Err(e) => {
-                        self.diff_content = format!("Error running git diff: {}", e);
+                        self.set_diff_content(format!("Error running git diff: {}", e));
                         self.view_mode = ViewMode::Diff;
                     }
                 }
@@ -402,19 +655,727 @@ impl App {
         Ok(())
     }
 
+    /// Side a combined diff or review starts on: the split layout's staged pane
+    /// shows the index, everything else the worktree.
+    fn default_diff_mode(&self) -> git::DiffMode {
+        if self.layout == AppLayout::Split && self.focus == Focus::Staged {
+            git::DiffMode::Index
+        } else {
+            git::DiffMode::Worktree
+        }
+    }
+
+    /// Switches the diff on screen to the next pair of sides (index vs worktree,
+    /// HEAD vs index, HEAD vs worktree).
+    pub fn cycle_diff_mode(&mut self) -> Result<()> {
+        if matches!(self.diff_target, Some(DiffTarget::Commit(..))) {
+            return Ok(());
+        }
+        if self.diff_untracked {
+            self.status_message = Some("Untracked file has no index or HEAD side".to_string());
+            return Ok(());
+        }
+
+        self.diff_mode = self.diff_mode.next();
+        if self.patch_mode && self.diff_mode == git::DiffMode::Head {
+            self.toggle_patch_mode();
+        }
+        self.diff_expanded.clear();
+        self.exit_line_mode();
+        self.diff_scroll = 0;
+        self.selected_hunk_idx = None;
+        self.reload_diff()
+    }
+
+    /// Shows one diff for every file under the selected directory or visual
+    /// selection. The split layout's staged pane shows the index side; otherwise
+    /// the worktree side is preferred, falling back to the index when clean.
+    fn show_combined_diff(&mut self) -> Result<()> {
+        let paths: Vec<String> = self
+            .selected_nodes()
+            .iter()
+            .map(|n| n.full_path.clone())
+            .collect();
+        if paths.is_empty() {
+            return Ok(());
+        }
+        if let [path] = paths.as_slice() {
+            if !self.is_visual_mode && git::preview::is_untracked_dir(path)? {
+                return self.show_preview(path.clone());
+            }
+        }
+
+        let mut mode = self.default_diff_mode();
+        let mut content = git::get_combined_diff(&paths, mode, &self.diff_options)?;
+        if content.is_empty() && mode == git::DiffMode::Worktree {
+            mode = git::DiffMode::Index;
+            content = git::get_combined_diff(&paths, mode, &self.diff_options)?;
+        }
+        if content.is_empty() {
+            self.status_message = Some("No changes in selection".to_string());
+            return Ok(());
+        }
+
+        self.is_visual_mode = false;
+        self.visual_origin = None;
+        self.diff_target = Some(DiffTarget::Paths(paths));
+        self.diff_mode = mode;
+        self.diff_untracked = false;
+        self.diff_folded.clear();
+        self.diff_expanded.clear();
+        self.open_diff(content);
+        Ok(())
+    }
+
+    /// Shows what the viewed commit changed in the selected files or
+    /// directories.
+    fn show_commit_diff(&mut self, commit: String) -> Result<()> {
+        let paths: Vec<String> = self
+            .selected_nodes()
+            .iter()
+            .map(|n| n.full_path.clone())
+            .collect();
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let content = git::show_commit(&commit, &paths, &self.diff_options)?;
+
+        self.is_visual_mode = false;
+        self.visual_origin = None;
+        self.diff_target = Some(DiffTarget::Commit(commit, paths));
+        self.diff_mode = git::DiffMode::Head;
+        self.diff_untracked = false;
+        self.diff_folded.clear();
+        self.diff_expanded.clear();
+        self.open_diff(content);
+        Ok(())
+    }
+
+    /// Shows what an untracked directory holds, or the capped contents of an
+    /// untracked file, instead of a new-file diff.
+    fn show_preview(&mut self, path: String) -> Result<()> {
+        let content = git::preview::preview_untracked(&path)?;
+        self.diff_target = Some(DiffTarget::Preview(path));
+        self.diff_mode = git::DiffMode::Worktree;
+        self.diff_untracked = true;
+        self.diff_folded.clear();
+        self.diff_expanded.clear();
+        self.open_diff(content);
+        Ok(())
+    }
+
+    /// Opens every changed file as one diff in patch mode, so hunks can be
+    /// reviewed and staged across files. In the split layout the staged pane
+    /// reviews the index instead.
+    pub fn open_review(&mut self) -> Result<()> {
+        if !self.diff_options.supports_patch() {
+            self.status_message =
+                Some("Hunks can't be staged while whitespace or context is hidden".to_string());
+            return Ok(());
+        }
+        let mode = self.default_diff_mode();
+        let content = git::get_diff_paths(&[], mode, &self.diff_options)?;
+        if content.is_empty() {
+            self.status_message = Some("Nothing to review".to_string());
+            return Ok(());
+        }
+
+        self.diff_target = Some(DiffTarget::Review);
+        self.diff_mode = mode;
+        self.diff_untracked = false;
+        self.diff_folded.clear();
+        self.diff_expanded.clear();
+        self.open_diff(content);
+        if !self.patch_mode {
+            self.toggle_patch_mode();
+        }
+        Ok(())
+    }
+
+    /// Shows freshly read diff text. Diffs over `LARGE_DIFF_LINES` are held
+    /// back: a multi-file diff opens with every file folded so files load one
+    /// at a time, a single file waits for `L`.
+    fn open_diff(&mut self, mut content: String) {
+        self.diff_deferred = false;
+        self.load_image_diff();
+        let lines = content.lines().count();
+        if lines > LARGE_DIFF_LINES {
+            let (files, _) = git::patch::parse_diff(&content);
+            if files.len() > 1 {
+                self.diff_folded = files.into_iter().map(|f| f.path).collect();
+                content = git::patch::fold_files(&content, &self.diff_folded);
+                self.status_message = Some(format!(
+                    "Large diff ({} lines): files folded, Tab to unfold",
+                    lines
+                ));
+            } else {
+                self.diff_deferred = true;
+            }
+        }
+        self.set_diff_content(content);
+        self.diff_scroll = 0;
+        self.diff_hscroll = 0;
+        self.view_mode = ViewMode::Diff;
+    }
+
+    /// Shows a large diff that `open_diff` held back.
+    pub fn load_deferred_diff(&mut self) {
+        if !self.diff_deferred {
+            return;
+        }
+        self.diff_deferred = false;
+        if self.diff_target == Some(DiffTarget::Review) && !self.patch_mode {
+            self.toggle_patch_mode();
+        }
+    }
+
     pub fn close_diff(&mut self) {
+        if self.patch_mode {
+            self.toggle_patch_mode();
+        }
+        self.view_mode = std::mem::replace(&mut self.diff_return, ViewMode::Tree);
+        self.set_diff_content(String::new());
+        self.diff_target = None;
+        self.diff_deferred = false;
+        self.image_diff = None;
+    }
+
+    /// Decodes both sides of the diffed file when it is an image, so the diff
+    /// view can draw them instead of "Binary files differ".
+    fn load_image_diff(&mut self) {
+        self.image_diff = match &self.diff_target {
+            Some(DiffTarget::File(path)) if image_view::is_image(path) => {
+                ImageDiff::load(path, self.diff_mode, self.diff_untracked).ok()
+            }
+            _ => None,
+        };
+    }
+
+    /// Adds the expanded context to a freshly read diff, then folds files.
+    fn prepare_diff(&self, content: &str) -> String {
+        let from_worktree = self.diff_mode.shows_worktree();
+        let expanded = git::patch::expand_context(content, &self.diff_expanded, |path| {
+            let file = if from_worktree {
+                git::read_worktree_file(path)
+            } else {
+                git::show_file("", path)
+            };
+            file.ok()
+                .map(|f| f.lines().map(|l| l.to_string()).collect())
+        });
+        git::patch::fold_files(&expanded, &self.diff_folded)
+    }
+
+    /// Re-reads the diff currently on screen, keeping scroll and hunk selection.
+    pub fn reload_diff(&mut self) -> Result<()> {
+        self.load_image_diff();
+        let content = match &self.diff_target {
+            Some(DiffTarget::File(path)) => {
+                let content = git::get_diff(
+                    path,
+                    self.diff_mode,
+                    self.diff_untracked,
+                    &self.diff_options,
+                )?;
+                if content.is_empty() && !self.diff_untracked {
+                    "(No diff or binary file)".to_string()
+                } else {
+                    self.prepare_diff(&content)
+                }
+            }
+            Some(DiffTarget::Paths(paths)) => {
+                let content = git::get_combined_diff(paths, self.diff_mode, &self.diff_options)?;
+                if content.is_empty() {
+                    "(No changes left in selection)".to_string()
+                } else {
+                    self.prepare_diff(&content)
+                }
+            }
+            Some(DiffTarget::Preview(path)) => git::preview::preview_untracked(path)?,
+            Some(DiffTarget::Commit(commit, paths)) => {
+                git::show_commit(commit, paths, &self.diff_options)?
+            }
+            Some(DiffTarget::Review) => {
+                let content = git::get_diff_paths(&[], self.diff_mode, &self.diff_options)?;
+                if content.is_empty() {
+                    "(Nothing left to review)".to_string()
+                } else {
+                    self.prepare_diff(&content)
+                }
+            }
+            None => return Ok(()),
+        };
+        self.set_diff_content(content);
+
+        if self.patch_mode {
+            let (files, hunks) = git::patch::parse_diff(&self.diff_content);
+            self.diff_files = files;
+            self.diff_hunks = hunks;
+            self.selected_hunk_idx = if self.diff_hunks.is_empty() {
+                None
+            } else {
+                Some(
+                    self.selected_hunk_idx
+                        .unwrap_or(0)
+                        .min(self.diff_hunks.len() - 1),
+                )
+            };
+            self.jump_to_hunk();
+        }
+        Ok(())
+    }
+
+    /// Applies `change` to the diff options, then re-reads the diff and the tree
+    /// so line stats match. Patch mode is left when the new diff can't be staged.
+    pub fn change_diff_options(
+        &mut self,
+        change: impl FnOnce(&mut git::DiffOptions),
+    ) -> Result<()> {
+        change(&mut self.diff_options);
+        if self.patch_mode && !self.diff_options.supports_patch() {
+            self.toggle_patch_mode();
+            self.status_message =
+                Some("Left patch mode: whitespace or context is hidden".to_string());
+        }
+        self.reload_diff()?;
+        self.refresh()
+    }
+
+    /// Switches between unified and side-by-side diffs and remembers the choice.
+    pub fn toggle_diff_layout(&mut self) -> Result<()> {
+        self.diff_layout = self.diff_layout.next();
+        git::set_config("twig.diff.layout", self.diff_layout.as_str())
+    }
+
+    /// Turns syntax highlighting in diffs on or off and remembers the choice.
+    pub fn toggle_syntax_highlight(&mut self) -> Result<()> {
+        self.syntax_highlight = !self.syntax_highlight;
+        let value = if self.syntax_highlight {
+            "true"
+        } else {
+            "false"
+        };
+        git::set_config("twig.diff.syntax", value)
+    }
+
+    /// Opens the whole file behind the tree selection (or the diff on screen)
+    /// with its changes marked, on the side the diff would show.
+    pub fn open_file_view(&mut self) -> Result<()> {
+        let (path, side, untracked) = if self.view_mode == ViewMode::Diff {
+            let Some(path) = self.focused_diff_file() else {
+                return Ok(());
+            };
+            let side = if self.diff_mode == git::DiffMode::Index {
+                FileSide::Index
+            } else {
+                FileSide::Worktree
+            };
+            (path, side, self.diff_untracked)
+        } else {
+            let nodes = self.selected_nodes();
+            let Some(node) = nodes.first() else {
+                return Ok(());
+            };
+            if node.is_dir {
+                self.status_message = Some("Select a file to view".to_string());
+                return Ok(());
+            }
+            let side = if node.raw_status.contains('+') {
+                FileSide::Index
+            } else {
+                FileSide::Worktree
+            };
+            (node.full_path.clone(), side, node.raw_status == "??")
+        };
+
+        self.file_view = Some(FileView::open(&path, side, untracked, self.view_mode)?);
+        self.view_mode = ViewMode::File;
+        self.diff_search_query.clear();
+        self.diff_matches.clear();
+        self.current_diff_match = None;
+        self.diff_hscroll = 0;
+        Ok(())
+    }
+
+    /// The file of the selected hunk in patch mode, otherwise the file at the
+    /// top of the diff view.
+    fn focused_diff_file(&self) -> Option<String> {
+        let (files, _) = git::patch::parse_diff(&self.diff_content);
+        let file = match self.selected_hunk_idx.and_then(|i| self.diff_hunks.get(i)) {
+            Some(hunk) if self.patch_mode => files.get(hunk.file),
+            _ => files
+                .iter()
+                .rev()
+                .find(|f| f.display_start <= self.diff_scroll)
+                .or(files.first()),
+        };
+        file.map(|f| f.path.clone())
+    }
+
+    /// First new-side line (0-based) of the selected hunk in patch mode,
+    /// otherwise of the hunk at the top of the diff view, or of the first
+    /// hunk of `path` when its header is at the top.
+    fn focused_diff_line(&self, path: &str) -> Option<usize> {
+        let (files, hunks) = git::patch::parse_diff(&self.diff_content);
+        let hunk = match self.selected_hunk_idx.and_then(|i| self.diff_hunks.get(i)) {
+            Some(hunk) if self.patch_mode => hunk,
+            _ => {
+                let in_file: Vec<_> = hunks
+                    .iter()
+                    .filter(|h| files.get(h.file).is_some_and(|f| f.path == path))
+                    .collect();
+                in_file
+                    .iter()
+                    .rev()
+                    .find(|h| h.display_start <= self.diff_scroll)
+                    .or(in_file.first())
+                    .copied()?
+            }
+        };
+        let (_, _, new_start, _, _) = git::patch::parse_hunk_header(&hunk.header)?;
+        Some(new_start.saturating_sub(1))
+    }
+
+    /// Blames the file selected in the tree, or the one on screen in the diff
+    /// with the line of the hunk in focus selected.
+    pub fn open_blame(&mut self) -> Result<()> {
+        let mut line = None;
+        let path = if self.view_mode == ViewMode::Diff {
+            if self.diff_untracked || matches!(self.diff_target, Some(DiffTarget::Commit(..))) {
+                return Ok(());
+            }
+            match self.focused_diff_file() {
+                Some(path) => {
+                    line = self.focused_diff_line(&path);
+                    path
+                }
+                None => return Ok(()),
+            }
+        } else {
+            let nodes = self.selected_nodes();
+            let Some(node) = nodes.first() else {
+                return Ok(());
+            };
+            if node.is_dir {
+                self.status_message = Some("Select a file to blame".to_string());
+                return Ok(());
+            }
+            if node.raw_status == "??" {
+                self.status_message = Some("Untracked file has no history".to_string());
+                return Ok(());
+            }
+            node.full_path.clone()
+        };
+
+        let mut view = BlameView::open(&path)?;
+        if let Some(line) = line {
+            view.move_by(line as isize);
+            view.cursor.scroll = view.cursor.selected;
+        }
+        view.previous_diff = self.save_diff();
+        self.blame_view = Some(view);
+        self.view_mode = ViewMode::Blame;
+        self.diff_hscroll = 0;
+        Ok(())
+    }
+
+    /// Goes back to where blame was opened, bringing back the diff that a
+    /// commit opened from blame replaced.
+    pub fn close_blame(&mut self) -> Result<()> {
+        let Some(view) = self.blame_view.take() else {
+            return Ok(());
+        };
+        self.restore_diff(view.previous_diff)
+    }
+
+    /// Opens the commit of the selected blame line. Uncommitted lines open
+    /// the file's changes against HEAD instead.
+    pub fn show_blame_commit(&mut self) -> Result<()> {
+        let Some(view) = self.blame_view.as_ref() else {
+            return Ok(());
+        };
+        let Some(line) = view.selected_line() else {
+            return Ok(());
+        };
+        let target = if line.is_uncommitted() {
+            DiffTarget::File(view.path.clone())
+        } else {
+            DiffTarget::Commit(line.commit.clone(), Vec::new())
+        };
+        self.open_diff_from(target, ViewMode::Blame)
+    }
+
+    /// Lists the commits that touched the selected node, or the file on
+    /// screen in the diff.
+    pub fn open_history(&mut self) -> Result<()> {
+        let (path, is_dir) = if self.view_mode == ViewMode::Diff {
+            if self.diff_untracked {
+                return Ok(());
+            }
+            match self.focused_diff_file() {
+                Some(path) => (path, false),
+                None => return Ok(()),
+            }
+        } else {
+            let nodes = self.selected_nodes();
+            let Some(node) = nodes.first() else {
+                return Ok(());
+            };
+            if node.raw_status == "??" {
+                self.status_message = Some("Untracked file has no history".to_string());
+                return Ok(());
+            }
+            (node.full_path.clone(), node.is_dir)
+        };
+
+        let mut view = HistoryView::open(&path, is_dir)?;
+        if view.entries.is_empty() {
+            self.status_message = Some(format!("No commits touch {}", path));
+            return Ok(());
+        }
+        view.previous_diff = self.save_diff();
+        self.history_view = Some(view);
+        self.view_mode = ViewMode::History;
+        Ok(())
+    }
+
+    pub fn close_history(&mut self) -> Result<()> {
+        let Some(view) = self.history_view.take() else {
+            return Ok(());
+        };
+        self.restore_diff(view.previous_diff)
+    }
+
+    /// Opens the selected commit's diff of the file or directory.
+    pub fn show_history_commit(&mut self) -> Result<()> {
+        let Some(view) = self.history_view.as_ref() else {
+            return Ok(());
+        };
+        let Some(entry) = view.selected_entry() else {
+            return Ok(());
+        };
+        let paths = if entry.paths.is_empty() {
+            vec![view.path.clone()]
+        } else {
+            entry.paths.clone()
+        };
+        let target = DiffTarget::Commit(entry.commit.clone(), paths);
+        self.open_diff_from(target, ViewMode::History)
+    }
+
+    /// Opens the commit graph. From a commit's tree, goes back to it instead.
+    pub fn open_log(&mut self) -> Result<()> {
+        if self.viewed_commit.is_some() {
+            if self.view_mode == ViewMode::Diff {
+                self.close_diff();
+            }
+            return self.close_commit_tree();
+        }
+
+        let mut view = LogView::open(self.layout)?;
+        if view.entries.is_empty() {
+            self.status_message = Some("No commits yet".to_string());
+            return Ok(());
+        }
+        view.previous_diff = self.save_diff();
+        self.log_view = Some(view);
+        self.view_mode = ViewMode::Log;
+        Ok(())
+    }
+
+    pub fn close_log(&mut self) -> Result<()> {
+        let Some(view) = self.log_view.take() else {
+            return Ok(());
+        };
+        self.restore_diff(view.previous_diff)
+    }
+
+    /// Shows the files the selected commit changed in the tree, read-only.
+    pub fn open_commit_tree(&mut self) -> Result<()> {
+        let Some(entry) = self.log_view.as_ref().and_then(|v| v.selected_entry()) else {
+            return Ok(());
+        };
+        self.viewed_commit = Some(entry.clone());
+        self.layout = AppLayout::Unified;
+        self.leave_tree_selection();
         self.view_mode = ViewMode::Tree;
-        self.diff_content.clear();
+        self.refresh()
     }
 
-    pub fn scroll_diff(&mut self, amount: i16) {
-        if amount > 0 {
-            self.diff_scroll = self.diff_scroll.saturating_add(amount as u16);
+    /// Goes back from a commit's tree to the log.
+    pub fn close_commit_tree(&mut self) -> Result<()> {
+        self.viewed_commit = None;
+        if let Some(view) = &self.log_view {
+            self.layout = view.tree_layout;
+        }
+        self.leave_tree_selection();
+        self.view_mode = ViewMode::Log;
+        self.refresh()
+    }
+
+    /// Drops the search and selection of a tree that is being swapped out.
+    fn leave_tree_selection(&mut self) {
+        self.search_query.clear();
+        self.is_visual_mode = false;
+        self.visual_origin = None;
+        self.reset_selection();
+    }
+
+    /// Shows `target` against HEAD in the diff view, going back to `from`
+    /// when it is closed.
+    fn open_diff_from(&mut self, target: DiffTarget, from: ViewMode) -> Result<()> {
+        self.diff_target = Some(target);
+        self.diff_mode = git::DiffMode::Head;
+        self.diff_untracked = false;
+        self.diff_folded.clear();
+        self.diff_expanded.clear();
+        self.reload_diff()?;
+        self.diff_scroll = 0;
+        self.diff_hscroll = 0;
+        self.diff_return = from;
+        self.view_mode = ViewMode::Diff;
+        Ok(())
+    }
+
+    /// Puts the diff on screen aside before a view opened from it replaces it.
+    fn save_diff(&mut self) -> Option<SavedDiff> {
+        if self.view_mode != ViewMode::Diff {
+            return None;
+        }
+        if self.patch_mode {
+            self.toggle_patch_mode();
+        }
+        self.diff_hscroll = 0;
+        self.diff_target
+            .clone()
+            .map(|target| (target, self.diff_mode, self.diff_return))
+    }
+
+    /// Brings back a diff put aside by `save_diff`, or goes back to the tree.
+    fn restore_diff(&mut self, saved: Option<SavedDiff>) -> Result<()> {
+        let Some((target, mode, diff_return)) = saved else {
+            self.close_diff();
+            return Ok(());
+        };
+        self.diff_target = Some(target);
+        self.diff_mode = mode;
+        self.diff_return = diff_return;
+        self.reload_diff()?;
+        self.view_mode = ViewMode::Diff;
+        Ok(())
+    }
+
+    /// Goes back to the view the file was opened from, re-reading a diff that
+    /// staging from the file view may have changed.
+    pub fn close_file_view(&mut self) -> Result<()> {
+        let Some(view) = self.file_view.take() else {
+            return Ok(());
+        };
+        self.view_mode = view.return_to;
+        self.diff_search_query.clear();
+        self.diff_matches.clear();
+        self.current_diff_match = None;
+        if self.view_mode == ViewMode::Diff {
+            self.reload_diff()?;
+        }
+        Ok(())
+    }
+
+    /// Shows the next version of the file (worktree, index, HEAD).
+    pub fn cycle_file_side(&mut self) -> Result<()> {
+        let Some(view) = self.file_view.as_mut() else {
+            return Ok(());
+        };
+        if view.untracked {
+            self.status_message = Some("Untracked file has no index or HEAD side".to_string());
+            return Ok(());
+        }
+        let previous = view.side;
+        view.side = view.side.next();
+        view.selected = None;
+        if let Err(e) = view.reload() {
+            view.side = previous;
+            view.reload()?;
+            self.status_message = Some(format!("No {} version: {}", previous.next().as_str(), e));
+        }
+        Ok(())
+    }
+
+    /// Stages the selected change of the worktree version, or unstages it from
+    /// the index version.
+    pub fn stage_file_change(&mut self) -> Result<()> {
+        let Some(view) = self.file_view.as_ref() else {
+            return Ok(());
+        };
+        if !view.side.can_stage() {
+            self.status_message = Some("Switch to the worktree or index to stage".to_string());
+            return Ok(());
+        }
+        let (Some(hunk), Some(file)) = (view.selected.and_then(|i| view.hunks.get(i)), &view.file)
+        else {
+            self.status_message = Some("Select a change with ] first".to_string());
+            return Ok(());
+        };
+
+        let patch = git::patch::build_patch(&file.headers, hunk);
+        let action = if view.side == FileSide::Index {
+            git::patch::apply_patch_text(&patch, true, true)?;
+            StageAction::UnstageHunk(patch)
         } else {
-            self.diff_scroll = self.diff_scroll.saturating_sub((-amount) as u16);
+            git::patch::apply_patch_text(&patch, true, false)?;
+            StageAction::StageHunk(patch)
+        };
+        self.history.push_action(vec![view.path.clone()], action);
+
+        if let Some(view) = self.file_view.as_mut() {
+            // A staged hunk of an untracked file makes it tracked
+            view.untracked = false;
+            view.reload()?;
+        }
+        self.refresh()
+    }
+
+    /// Replaces the diff text, marking the diff model out of date.
+    fn set_diff_content(&mut self, content: String) {
+        self.diff_content = content;
+        self.diff_model.invalidate();
+    }
+
+    /// Brings the cached diff model in line with the diff text and view settings.
+    pub fn sync_diff_model(&mut self) {
+        self.diff_model
+            .update(&self.diff_content, self.diff_layout, self.fold_unchanged);
+    }
+
+    pub fn scroll_diff(&mut self, amount: i16) {
+        // diff_scroll stays a raw diff line but moves a whole row at a time, so
+        // side-by-side pairs and folds are never split
+        if self.diff_tool.is_none() || self.patch_mode {
+            self.sync_diff_model();
+            let rows = &self.diff_model.rows;
+            let current = side_by_side::row_at(rows, self.diff_scroll);
+            let target = current
+                .saturating_add_signed(amount as isize)
+                .min(rows.len().saturating_sub(1));
+            self.diff_scroll = rows.get(target).map(|r| r.first_raw()).unwrap_or(0);
+            return;
+        }
+
+        self.diff_scroll = self.diff_scroll.saturating_add_signed(amount as isize);
+    }
+
+    /// Scrolls diff lines sideways; wrapped lines have nothing to scroll.
+    pub fn scroll_diff_horizontal(&mut self, amount: isize) {
+        if !self.diff_wrap {
+            self.diff_hscroll = self.diff_hscroll.saturating_add_signed(amount);
         }
     }
 
+    pub fn toggle_diff_wrap(&mut self) {
+        self.diff_wrap = !self.diff_wrap;
+        self.diff_hscroll = 0;
+    }
+
     pub fn next(&mut self) {
         let (nodes, state) = match self.layout {
             AppLayout::Unified | AppLayout::Compact | AppLayout::EasterEgg => {
@@ -641,24 +1602,128 @@ impl App {
         Ok(())
     }
 
-    pub fn undo_staging(&mut self) -> Result<()> {
-        if let Some(entry) = self.history.undo() {
-            for path in entry.paths {
-                let to_unstage = entry.action == StageAction::Stage;
-                git::toggle_stage(&path, to_unstage)?;
+    pub fn undo_staging(&mut self) -> Result<()> {
+        if let Some(entry) = self.history.undo() {
+            match entry.action {
+                StageAction::Stage | StageAction::Unstage => {
+                    for path in entry.paths {
+                        let to_unstage = entry.action == StageAction::Stage;
+                        git::toggle_stage(&path, to_unstage)?;
+                    }
+                }
+                StageAction::Discard(backups) => {
+                    for backup in backups {
+                        git::discard::restore_backup(&backup.path, backup.before.as_deref())?;
+                    }
+                }
+                StageAction::StageHunk(patch) => git::patch::apply_patch_text(&patch, true, true)?,
+                StageAction::UnstageHunk(patch) => {
+                    git::patch::apply_patch_text(&patch, true, false)?
+                }
+            }
+            self.refresh()?;
+            self.reload_diff()?;
+        }
+        Ok(())
+    }
+
+    pub fn redo_staging(&mut self) -> Result<()> {
+        if let Some(entry) = self.history.redo() {
+            match entry.action {
+                StageAction::Stage | StageAction::Unstage => {
+                    for path in entry.paths {
+                        let to_unstage = entry.action == StageAction::Unstage;
+                        git::toggle_stage(&path, to_unstage)?;
+                    }
+                }
+                StageAction::Discard(backups) => {
+                    for backup in backups {
+                        git::discard::restore_backup(&backup.path, backup.after.as_deref())?;
+                    }
+                }
+                StageAction::StageHunk(patch) => git::patch::apply_patch_text(&patch, true, false)?,
+                StageAction::UnstageHunk(patch) => {
+                    git::patch::apply_patch_text(&patch, true, true)?
+                }
+            }
+            self.refresh()?;
+            self.reload_diff()?;
+        }
+        Ok(())
+    }
+
+    /// Asks for confirmation before throwing away worktree changes of the
+    /// selection (tree view) or the selected hunk (patch mode).
+    pub fn request_discard(&mut self) -> Result<()> {
+        if self.view_mode == ViewMode::Diff {
+            if self.patch_mode && self.diff_mode.shows_worktree() && !self.diff_untracked {
+                if let Some(i) = self.selected_hunk_idx {
+                    self.pending_discard = Some(DiscardRequest::Hunk(i));
+                }
             }
-            self.refresh()?;
+            return Ok(());
+        }
+
+        let paths: Vec<String> = self
+            .selected_nodes()
+            .iter()
+            .map(|n| n.full_path.clone())
+            .collect();
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let changes = git::discard::get_worktree_changes(&paths)?;
+        if !changes.is_empty() {
+            self.pending_discard = Some(DiscardRequest::Files(changes));
         }
         Ok(())
     }
 
-    pub fn redo_staging(&mut self) -> Result<()> {
-        if let Some(entry) = self.history.redo() {
-            for path in entry.paths {
-                let to_unstage = entry.action == StageAction::Unstage;
-                git::toggle_stage(&path, to_unstage)?;
+    pub fn cancel_discard(&mut self) {
+        self.pending_discard = None;
+    }
+
+    pub fn confirm_discard(&mut self) -> Result<()> {
+        let Some(request) = self.pending_discard.take() else {
+            return Ok(());
+        };
+
+        match request {
+            DiscardRequest::Files(changes) => {
+                let (backups, result) = git::discard::discard_files(&changes);
+                if !backups.is_empty() {
+                    let paths = backups.iter().map(|b| b.path.clone()).collect();
+                    self.history
+                        .push_action(paths, StageAction::Discard(backups));
+                }
+                self.is_visual_mode = false;
+                self.visual_origin = None;
+                self.refresh()?;
+                result?;
+            }
+            DiscardRequest::Hunk(i) => {
+                let Some(hunk) = self.diff_hunks.get(i) else {
+                    return Ok(());
+                };
+                let file = &self.diff_files[hunk.file];
+                let path = file.path.clone();
+                let before = git::discard::backup_file(&path)?;
+                git::patch::apply_patch(&file.headers, hunk, false, true)?;
+                // The hunk is gone now, so its backup is kept even if the
+                // file can't be read back
+                let after = git::discard::backup_file(&path);
+                let backup = git::FileBackup {
+                    path: path.clone(),
+                    before,
+                    after: after.as_ref().ok().cloned().flatten(),
+                };
+                self.history
+                    .push_action(vec![path], StageAction::Discard(vec![backup]));
+                self.refresh()?;
+                self.reload_diff()?;
+                after?;
             }
-            self.refresh()?;
         }
         Ok(())
     }
@@ -693,6 +1758,10 @@ impl App {
                 if node.is_dir && self.collapsed_paths.contains(&node.full_path) {
                     self.collapsed_paths.remove(&node.full_path);
                     self.refresh()?;
+                } else if node.raw_status == "??" && node.full_path.ends_with('/') {
+                    // A summarized untracked directory: list what's inside
+                    self.untracked.expanded.insert(node.full_path.clone());
+                    self.refresh()?;
                 }
             }
         }
@@ -736,7 +1805,15 @@ impl App {
     }
 
     pub fn collapse_all(&mut self) -> Result<()> {
-        let tree = git::build_tree_from_git(false, false, false)?;
+        let ignored = self.filter_mode == FilterMode::Ignored;
+        let tree = git::build_tree_from_git(
+            false,
+            false,
+            false,
+            ignored,
+            &self.diff_options,
+            &mut self.untracked,
+        )?;
         if let Some(root) = tree {
             root.get_all_dir_paths(&mut self.collapsed_paths);
             self.refresh()?;
@@ -789,6 +1866,158 @@ impl App {
         Ok(())
     }
 
+    pub fn open_branches(&mut self) -> Result<()> {
+        self.branch_modal = Some(BranchModal::open()?);
+        Ok(())
+    }
+
+    /// Checks out the selected branch, asking first when tracked files have
+    /// changes.
+    pub fn checkout_branch(&mut self) -> Result<()> {
+        let Some(modal) = self.branch_modal.as_mut() else {
+            return Ok(());
+        };
+        let Some(branch) = modal.selected() else {
+            return Ok(());
+        };
+        if branch.is_head {
+            self.status_message = Some(format!("Already on {}", branch.name));
+            return Ok(());
+        }
+        if git::branch::has_local_changes()? {
+            modal.confirm = Some(BranchConfirm::Checkout);
+            return Ok(());
+        }
+        self.finish_checkout()
+    }
+
+    fn finish_checkout(&mut self) -> Result<()> {
+        let Some(modal) = self.branch_modal.as_ref() else {
+            return Ok(());
+        };
+        let Some(branch) = modal.selected() else {
+            return Ok(());
+        };
+        git::branch::checkout(branch, modal.has_local(branch.local_name()))?;
+        self.status_message = Some(format!("Switched to {}", branch.local_name()));
+        self.branch_modal = None;
+        self.refresh()
+    }
+
+    /// Asks to delete the selected local branch, warning when it isn't merged
+    /// into HEAD.
+    pub fn request_branch_delete(&mut self) -> Result<()> {
+        let Some(modal) = self.branch_modal.as_mut() else {
+            return Ok(());
+        };
+        let Some(branch) = modal.selected() else {
+            return Ok(());
+        };
+        if branch.is_remote {
+            self.status_message = Some("Only local branches can be deleted here".to_string());
+        } else if branch.is_head {
+            self.status_message = Some("Can't delete the current branch".to_string());
+        } else {
+            let merged = git::branch::is_merged(&branch.name)?;
+            modal.confirm = Some(BranchConfirm::Delete {
+                name: branch.name.clone(),
+                merged,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn answer_branch_confirm(&mut self, yes: bool) -> Result<()> {
+        let Some(confirm) = self.branch_modal.as_mut().and_then(|m| m.confirm.take()) else {
+            return Ok(());
+        };
+        if !yes {
+            return Ok(());
+        }
+        match confirm {
+            BranchConfirm::Checkout => self.finish_checkout(),
+            BranchConfirm::Delete { name, merged } => {
+                git::branch::delete(&name, !merged)?;
+                self.status_message = Some(format!("Deleted {}", name));
+                self.reload_branches(None)
+            }
+        }
+    }
+
+    /// Starts typing a branch name: a new branch from HEAD or the selected
+    /// branch, a new name for the selected branch, or its upstream.
+    pub fn start_branch_input(&mut self, kind: char) {
+        let Some(modal) = self.branch_modal.as_mut() else {
+            return;
+        };
+        let selected = modal.selected().cloned();
+        let input = match (kind, selected) {
+            ('n', _) => (
+                BranchInput::Create {
+                    start: "HEAD".to_string(),
+                },
+                String::new(),
+            ),
+            ('N', Some(branch)) => (BranchInput::Create { start: branch.name }, String::new()),
+            ('r' | 'u', Some(branch)) if branch.is_remote => {
+                self.status_message = Some("Select a local branch".to_string());
+                return;
+            }
+            ('r', Some(branch)) => (
+                BranchInput::Rename {
+                    old: branch.name.clone(),
+                },
+                branch.name,
+            ),
+            ('u', Some(branch)) => (
+                BranchInput::Upstream {
+                    branch: branch.name.clone(),
+                },
+                branch.upstream.unwrap_or_default(),
+            ),
+            _ => return,
+        };
+        modal.input = Some(input);
+    }
+
+    /// Runs what the typed branch name was for.
+    pub fn submit_branch_input(&mut self) -> Result<()> {
+        let Some((input, text)) = self.branch_modal.as_mut().and_then(|m| m.input.take()) else {
+            return Ok(());
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+        match input {
+            BranchInput::Create { start } => {
+                git::branch::create(text, &start)?;
+                self.status_message = Some(format!("Created {} from {}", text, start));
+                self.reload_branches(Some(text))
+            }
+            BranchInput::Rename { old } => {
+                git::branch::rename(&old, text)?;
+                self.status_message = Some(format!("Renamed {} to {}", old, text));
+                self.reload_branches(Some(text))?;
+                // The tree header names the current branch
+                self.refresh()
+            }
+            BranchInput::Upstream { branch } => {
+                git::branch::set_upstream(&branch, text)?;
+                self.status_message = Some(format!("{} now tracks {}", branch, text));
+                self.reload_branches(Some(&branch))
+            }
+        }
+    }
+
+    fn reload_branches(&mut self, name: Option<&str>) -> Result<()> {
+        match self.branch_modal.as_mut() {
+            Some(modal) => modal.reload(name),
+            None => Ok(()),
+        }
+    }
+
+    /// Finds the lines of the diff (or of the file view) containing the query.
     pub fn search_diff(&mut self) {
         self.diff_matches.clear();
         self.current_diff_match = None;
@@ -798,13 +2027,27 @@ impl App {
         }
 
         let query = self.diff_search_query.to_lowercase();
-        for (i, line) in self.diff_content.lines().enumerate() {
+        let file_lines = self
+            .file_view
+            .as_ref()
+            .filter(|_| self.view_mode == ViewMode::File)
+            .map(|v| &v.lines);
+        self.diff_matches = match file_lines {
+            Some(lines) => lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.to_lowercase().contains(&query))
+                .map(|(i, _)| i)
+                .collect(),
             // Strip ANSI codes for searching
-            let clean_line = strip_ansi_codes(line).to_lowercase();
-            if clean_line.contains(&query) {
-                self.diff_matches.push(i);
-            }
-        }
+            None => self
+                .diff_content
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| strip_ansi_codes(line).to_lowercase().contains(&query))
+                .map(|(i, _)| i)
+                .collect(),
+        };
 
         if !self.diff_matches.is_empty() {
             self.current_diff_match = Some(0);
@@ -831,24 +2074,167 @@ impl App {
         Ok(())
     }
 
+    pub fn open_ignore_dialog(&mut self) {
+        // Only untracked files, and directories holding nothing tracked, can
+        // be ignored
+        self.ignore_paths = self
+            .selected_nodes()
+            .iter()
+            .filter(|n| {
+                n.depth > 0
+                    && (n.raw_status == "??"
+                        || n.is_dir
+                            && git::preview::is_untracked_dir(&n.full_path).unwrap_or(false))
+            })
+            .map(|n| {
+                if n.is_dir && !n.full_path.ends_with('/') {
+                    format!("{}/", n.full_path)
+                } else {
+                    n.full_path.clone()
+                }
+            })
+            .collect();
+
+        if self.ignore_paths.is_empty() {
+            self.status_message = Some("Only untracked files can be ignored".to_string());
+            return;
+        }
+
+        self.is_visual_mode = false;
+        self.visual_origin = None;
+        self.ignore_target = IgnoreTarget::Root;
+        self.show_ignore_dialog = true;
+        self.update_ignore_candidates();
+    }
+
+    pub fn close_ignore_dialog(&mut self) {
+        self.show_ignore_dialog = false;
+        self.ignore_paths.clear();
+        self.ignore_candidates.clear();
+        self.ignore_preview.clear();
+    }
+
+    /// Directory the selected ignore file lives in, relative to the repo root.
+    fn ignore_base(&self) -> Option<String> {
+        match self.ignore_target {
+            IgnoreTarget::Root | IgnoreTarget::Exclude => Some(String::new()),
+            IgnoreTarget::Local => git::ignore::common_parent(&self.ignore_paths),
+            IgnoreTarget::Global => None,
+        }
+    }
+
+    pub fn cycle_ignore_target(&mut self) {
+        self.ignore_target = self.ignore_target.next();
+        // A local .gitignore only makes sense when everything shares a directory
+        if self.ignore_target == IgnoreTarget::Local
+            && git::ignore::common_parent(&self.ignore_paths).is_none()
+        {
+            self.ignore_target = self.ignore_target.next();
+        }
+        self.update_ignore_candidates();
+    }
+
+    fn update_ignore_candidates(&mut self) {
+        let base = self.ignore_base();
+        self.ignore_candidates =
+            git::ignore::candidate_patterns(&self.ignore_paths, base.as_deref());
+        self.ignore_state
+            .select(if self.ignore_candidates.is_empty() {
+                None
+            } else {
+                Some(0)
+            });
+        self.update_ignore_preview();
+    }
+
+    fn update_ignore_preview(&mut self) {
+        let base = self.ignore_base();
+        self.ignore_preview = self
+            .ignore_state
+            .selected()
+            .and_then(|i| self.ignore_candidates.get(i))
+            .and_then(|c| git::ignore::preview(&c.patterns, base.as_deref()).ok())
+            .unwrap_or_default();
+    }
+
+    pub fn move_ignore_selection(&mut self, down: bool) {
+        let len = self.ignore_candidates.len();
+        if len == 0 {
+            return;
+        }
+        let i = match self.ignore_state.selected() {
+            Some(i) if down => (i + 1) % len,
+            Some(i) => (i + len - 1) % len,
+            None => 0,
+        };
+        self.ignore_state.select(Some(i));
+        self.update_ignore_preview();
+    }
+
+    pub fn confirm_ignore(&mut self) -> Result<()> {
+        let candidate = self
+            .ignore_state
+            .selected()
+            .and_then(|i| self.ignore_candidates.get(i));
+
+        if let Some(candidate) = candidate {
+            let local_base = git::ignore::common_parent(&self.ignore_paths).unwrap_or_default();
+            let file = git::ignore::target_file(self.ignore_target, &local_base)?;
+            git::ignore::append_patterns(&file, &candidate.patterns)?;
+            self.close_ignore_dialog();
+            self.refresh()?;
+        }
+        Ok(())
+    }
+
     pub fn toggle_patch_mode(&mut self) {
         if self.view_mode != ViewMode::Diff {
             return;
         }
 
+        if !self.patch_mode && matches!(self.diff_target, Some(DiffTarget::Commit(..))) {
+            self.status_message = Some("Committed changes can't be staged".to_string());
+            return;
+        }
+        if !self.patch_mode && matches!(self.diff_target, Some(DiffTarget::Preview(_))) {
+            self.status_message = Some("Preview only: stage it with Space in the tree".to_string());
+            return;
+        }
+        if !self.patch_mode && self.image_diff.is_some() {
+            self.status_message = Some("Images have no hunks to stage".to_string());
+            return;
+        }
+        if !self.patch_mode && self.diff_deferred {
+            self.status_message = Some("Press L to load the diff first".to_string());
+            return;
+        }
+        if !self.patch_mode && self.diff_mode == git::DiffMode::Head {
+            // HEAD's hunks don't apply to an index that already has changes
+            self.status_message = Some(
+                "HEAD diffs can't be staged: press m for the index or worktree side".to_string(),
+            );
+            return;
+        }
+        if !self.patch_mode && !self.diff_options.supports_patch() {
+            self.status_message =
+                Some("Hunks can't be staged while whitespace or context is hidden".to_string());
+            return;
+        }
+
         self.patch_mode = !self.patch_mode;
         if self.patch_mode {
-            let (headers, hunks) = git::patch::parse_diff(&self.diff_content);
-            self.diff_headers = headers;
+            let (files, hunks) = git::patch::parse_diff(&self.diff_content);
+            self.diff_files = files;
             self.diff_hunks = hunks;
             if !self.diff_hunks.is_empty() {
                 self.selected_hunk_idx = Some(0);
                 self.jump_to_hunk();
             }
         } else {
-            self.diff_headers.clear();
+            self.diff_files.clear();
             self.diff_hunks.clear();
             self.selected_hunk_idx = None;
+            self.exit_line_mode();
         }
     }
 
@@ -870,85 +2256,354 @@ impl App {
         }
     }
 
+    /// Folds or unfolds the file at the top of the diff view. Hunks of folded
+    /// files are hidden and skipped by hunk navigation.
+    pub fn toggle_diff_fold(&mut self) -> Result<()> {
+        let (files, _) = git::patch::parse_diff(&self.diff_content);
+        let top = self.diff_scroll;
+        let Some(file) = files.iter().rev().find(|f| f.display_start <= top) else {
+            return Ok(());
+        };
+        let path = file.path.clone();
+        if !self.diff_folded.remove(&path) {
+            self.diff_folded.insert(path.clone());
+        }
+
+        self.reload_diff()?;
+
+        // Stay on the toggled file rather than wherever the hunk selection landed
+        let (files, _) = git::patch::parse_diff(&self.diff_content);
+        if let Some(file) = files.iter().find(|f| f.path == path) {
+            self.diff_scroll = file.display_start;
+            if self.patch_mode && !self.diff_hunks.is_empty() {
+                let idx = self
+                    .diff_hunks
+                    .iter()
+                    .position(|h| h.display_start >= file.display_start)
+                    .unwrap_or(self.diff_hunks.len() - 1);
+                self.selected_hunk_idx = Some(idx);
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows `EXPAND_STEP` more lines of context above or below the selected
+    /// hunk (outside patch mode, the one at the top of the view), read from the
+    /// new side of the diff.
+    pub fn expand_hunk_context(&mut self, above: bool) -> Result<()> {
+        // Context comes from the worktree or index, not from past commits
+        if matches!(self.diff_target, Some(DiffTarget::Commit(..))) {
+            return Ok(());
+        }
+        let (files, hunks) = git::patch::parse_diff(&self.diff_content);
+        let top = self.diff_scroll;
+        let idx = if self.patch_mode {
+            self.selected_hunk_idx
+        } else {
+            hunks
+                .iter()
+                .rposition(|h| h.display_start <= top)
+                .or((!hunks.is_empty()).then_some(0))
+        };
+        let Some(idx) = idx.filter(|&i| i < hunks.len()) else {
+            return Ok(());
+        };
+        let Some(file) = files.get(hunks[idx].file) else {
+            return Ok(());
+        };
+        let nth = hunks[..idx]
+            .iter()
+            .filter(|h| h.file == hunks[idx].file)
+            .count();
+
+        let extra = self
+            .diff_expanded
+            .entry((file.path.clone(), nth))
+            .or_insert((0, 0));
+        if above {
+            extra.0 += EXPAND_STEP;
+        } else {
+            extra.1 += EXPAND_STEP;
+        }
+
+        let before = self.diff_content.lines().count();
+        self.reload_diff()?;
+        if self.diff_content.lines().count() == before {
+            self.status_message = Some("No more context to show".to_string());
+        }
+        if !self.patch_mode {
+            let (_, hunks) = git::patch::parse_diff(&self.diff_content);
+            if let Some(hunk) = hunks.get(idx) {
+                self.diff_scroll = hunk.display_start;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn toggle_fold_unchanged(&mut self) {
+        self.fold_unchanged = !self.fold_unchanged;
+    }
+
+    /// Selects the first hunk of the next file in a multi-file diff.
+    pub fn next_diff_file(&mut self) {
+        let Some(current) = self.selected_hunk_idx.and_then(|i| self.diff_hunks.get(i)) else {
+            return;
+        };
+        let file = current.file;
+        if let Some(i) = self.diff_hunks.iter().position(|h| h.file > file) {
+            self.selected_hunk_idx = Some(i);
+            self.jump_to_hunk();
+        }
+    }
+
+    /// Selects the first hunk of the current file, or of the previous one when
+    /// already there.
+    pub fn prev_diff_file(&mut self) {
+        let Some(i) = self.selected_hunk_idx else {
+            return;
+        };
+        let Some(file) = self.diff_hunks.get(i).map(|h| h.file) else {
+            return;
+        };
+        let first_of = |f: usize| self.diff_hunks.iter().position(|h| h.file == f);
+
+        let target = match first_of(file) {
+            Some(first) if first < i => Some(first),
+            _ => self.diff_hunks[..i]
+                .last()
+                .and_then(|prev| first_of(prev.file)),
+        };
+        if let Some(target) = target {
+            self.selected_hunk_idx = Some(target);
+            self.jump_to_hunk();
+        }
+    }
+
+    /// Position of the selected hunk as (file, files, hunk, hunks in file), 1-based.
+    pub fn hunk_progress(&self) -> Option<(usize, usize, usize, usize)> {
+        let i = self.selected_hunk_idx?;
+        let hunk = self.diff_hunks.get(i)?;
+        let same_file = |h: &&git::Hunk| h.file == hunk.file;
+        let hunk_pos = self.diff_hunks[..i].iter().filter(same_file).count() + 1;
+        let hunk_total = self.diff_hunks.iter().filter(same_file).count();
+        Some((hunk.file + 1, self.diff_files.len(), hunk_pos, hunk_total))
+    }
+
     fn jump_to_hunk(&mut self) {
         if let Some(i) = self.selected_hunk_idx {
             if let Some(hunk) = self.diff_hunks.get(i) {
-                // Adjust scroll to center the hunk
-                let target = hunk.display_start as u16;
-                // Simple version: jump to top
+                // Keep the file separator in view when entering a new file
+                let first_of_file = i == 0 || self.diff_hunks[i - 1].file != hunk.file;
+                let target = match self.diff_files.get(hunk.file) {
+                    Some(file) if first_of_file => file.display_start,
+                    _ => hunk.display_start,
+                };
                 self.diff_scroll = target;
             }
         }
     }
 
+    /// Stages the selected hunk of an unstaged diff, or unstages it when the
+    /// staged diff is on screen.
     pub fn stage_hunk(&mut self) -> Result<()> {
-        if let Some(i) = self.selected_hunk_idx {
-            if let Some(hunk) = self.diff_hunks.get(i) {
-                let _is_staged = if let Some(_idx) = self.unified_state.selected() {
-                    // This is tricky: we need to know if the CURRENT file is staged or not
-                    // But patch mode is generic.
-                    // Generally, if we are in Diff view, we are diffing a specific file node.
-                    // And we know the status of that node.
-                    // We should pass 'stage' boolean direction.
-                    // However, git apply --cached applies TO the index (staging it).
-                    // git apply --reverse --cached would UNSTAGE it.
-                    // We need to know if we are 'Adding' or 'Resetting'.
-                    // Let's rely on the raw_status of the selected node.
-                    false // FIXME: Logic needed below
-                } else {
-                    false
-                };
+        let Some(hunk) = self
+            .selected_hunk_idx
+            .and_then(|i| self.diff_hunks.get(i))
+            .cloned()
+        else {
+            return Ok(());
+        };
+        self.apply_hunk_to_index(&hunk)
+    }
 
-                // For now, let's assume this is mostly for STAGING (add -p).
-                // But unstage -p is also valid.
-                // We need to look up the node again.
-                // This is slightly inefficient but safe.
-
-                let node = self.get_selected_node();
-                if let Some(n) = node {
-                    let is_staged = n.raw_status.contains('+');
-                    if is_staged {
-                        // Unstage: git apply --cached --reverse
-                        // Not implemented in helper yet, but we can just use `git restore --patch`?
-                        // Or update apply_patch to support reverse.
-                        // Let's update apply_patch first.
-                        // For now, let's just support Staging (add -p equivalent).
-                        // If user tries to stage checks on a staged file, it does nothing or errors.
-
-                        // Actually, apply_patch takes 'headers'.
-                        // If we are unstaging, we might need --reverse.
-                        // Let's start with just handling Staging for v1.3.0 scope if complex.
-                        // roadmap says "Interactive Patch Staging".
-
-                        git::patch::apply_patch(&self.diff_headers, hunk, true)?;
-                    } else {
-                        // Stage: git apply --cached
-                        git::patch::apply_patch(&self.diff_headers, hunk, true)?;
-                    }
+    fn apply_hunk_to_index(&mut self, hunk: &git::Hunk) -> Result<()> {
+        let Some(file) = self.diff_files.get(hunk.file) else {
+            return Ok(());
+        };
+        let path = file.path.clone();
 
-                    // Refresh to show status change
-                    // self.refresh()?;
-                    // Ideally we stay in diff mode but refresh the underlying diff?
-                    // If we stage a hunk, it disappears from "Unstaged" diff.
-                    // So refreshing is good.
-                    self.refresh()?;
+        let patch = git::patch::build_patch(&file.headers, hunk);
+        let action = if self.diff_mode == git::DiffMode::Index {
+            git::patch::apply_patch_text(&patch, true, true)?;
+            StageAction::UnstageHunk(patch)
+        } else {
+            git::patch::apply_patch_text(&patch, true, false)?;
+            StageAction::StageHunk(patch)
+        };
+        self.history.push_action(vec![path], action);
 
-                    // Re-parse diff to keep index validity?
-                    // Or just close patch mode?
-                    // Most robust: close patch mode or re-parse.
-                    self.toggle_patch_mode(); // Close for safety
-                    self.show_diff()?; // Re-open diff (will fetch new content)
-                    self.toggle_patch_mode(); // Re-enable? Maybe annoying.
-                                              // Let's just exit patch mode and let user re-enter if they want more.
-                    self.toggle_patch_mode(); // Off
-                    self.show_diff()?;
-                }
-            }
+        // A staged hunk of an untracked file makes it tracked
+        self.diff_untracked = false;
+        self.refresh()?;
+        self.reload_diff()
+    }
+
+    fn is_change_line(&self, idx: usize) -> bool {
+        let line = self.diff_model.line(idx);
+        line.starts_with('+') || line.starts_with('-')
+    }
+
+    /// Scrolls just enough to keep the line cursor on screen.
+    fn scroll_to_line_cursor(&mut self) {
+        if self.line_cursor < self.diff_scroll {
+            self.diff_scroll = self.line_cursor;
+        } else if self.diff_height > 0 && self.line_cursor >= self.diff_scroll + self.diff_height {
+            self.diff_scroll = self.line_cursor + 1 - self.diff_height;
         }
-        Ok(())
     }
 
-    fn get_selected_node(&self) -> Option<&crate::node::FlatNode> {
+    /// Enters line selection on the selected hunk, with the cursor on its first change.
+    pub fn enter_line_mode(&mut self) {
+        self.sync_diff_model();
+        let Some(hunk) = self.selected_hunk_idx.and_then(|i| self.diff_hunks.get(i)) else {
+            return;
+        };
+        let (start, end) = (hunk.display_start, hunk.display_end);
+
+        if let Some(first) = (start + 1..=end).find(|&i| self.is_change_line(i)) {
+            self.line_mode = true;
+            self.line_cursor = first;
+            self.selected_lines.clear();
+            self.scroll_to_line_cursor();
+        }
+    }
+
+    pub fn exit_line_mode(&mut self) {
+        self.line_mode = false;
+        self.selected_lines.clear();
+    }
+
+    pub fn move_line_cursor(&mut self, down: bool) {
+        self.sync_diff_model();
+        let Some(hunk) = self.selected_hunk_idx.and_then(|i| self.diff_hunks.get(i)) else {
+            return;
+        };
+        let (start, end) = (hunk.display_start, hunk.display_end);
+
+        let next = if down {
+            (self.line_cursor + 1..=end).find(|&i| self.is_change_line(i))
+        } else {
+            (start + 1..self.line_cursor)
+                .rev()
+                .find(|&i| self.is_change_line(i))
+        };
+        if let Some(i) = next {
+            self.line_cursor = i;
+            self.scroll_to_line_cursor();
+        }
+    }
+
+    pub fn toggle_line_selection(&mut self) {
+        if !self.selected_lines.remove(&self.line_cursor) {
+            self.selected_lines.insert(self.line_cursor);
+        }
+    }
+
+    /// Stages (or unstages) just the selected lines of the hunk.
+    pub fn stage_lines(&mut self) -> Result<()> {
+        let Some(hunk) = self.selected_hunk_idx.and_then(|i| self.diff_hunks.get(i)) else {
+            return Ok(());
+        };
+
+        let offsets = self
+            .selected_lines
+            .iter()
+            .filter_map(|&i| i.checked_sub(hunk.display_start))
+            .collect();
+        let unstage = self.diff_mode == git::DiffMode::Index;
+        let Some(narrowed) = git::patch::select_lines(hunk, &offsets, unstage) else {
+            return Ok(());
+        };
+
+        self.exit_line_mode();
+        self.apply_hunk_to_index(&narrowed)
+    }
+
+    /// Splits the selected hunk at its unchanged context runs, selecting the first piece.
+    pub fn split_hunk(&mut self) {
+        let Some(i) = self.selected_hunk_idx else {
+            return;
+        };
+        let Some(hunk) = self.diff_hunks.get(i) else {
+            return;
+        };
+
+        let pieces = git::patch::split_hunk(hunk);
+        if pieces.len() < 2 {
+            self.status_message = Some("Hunk cannot be split further".to_string());
+            return;
+        }
+
+        self.diff_hunks.splice(i..=i, pieces);
+        // Re-render so every piece gets its own header and display range
+        self.set_diff_content(git::patch::render_diff(&self.diff_files, &self.diff_hunks));
+        let (files, hunks) = git::patch::parse_diff(&self.diff_content);
+        self.diff_files = files;
+        self.diff_hunks = hunks;
+        self.jump_to_hunk();
+    }
+
+    /// Writes the selected hunk to a file for the editor, returning its path.
+    pub fn prepare_hunk_edit(&self) -> Result<Option<std::path::PathBuf>> {
+        let Some(hunk) = self.selected_hunk_idx.and_then(|i| self.diff_hunks.get(i)) else {
+            return Ok(None);
+        };
+
+        let path = git::rev_parse(&["--git-path", "TWIG_EDIT_HUNK.diff"])?;
+        let direction = if self.diff_mode == git::DiffMode::Index {
+            "unstaged"
+        } else {
+            "staged"
+        };
+        let mut content = git::patch::build_patch(&self.diff_files[hunk.file].headers, hunk);
+        content.push_str(&format!(
+            "# Manual hunk edit mode -- the edited hunk will be {direction}.\n\
+             # To remove '-' lines, make them ' ' lines (context).\n\
+             # To remove '+' lines, delete them.\n\
+             # Lines starting with # will be removed.\n\
+             # Save an empty file to abort.\n"
+        ));
+        std::fs::write(&path, content)?;
+        Ok(Some(path))
+    }
+
+    /// Validates and applies the patch left behind by the editor.
+    pub fn finish_hunk_edit(&mut self, path: &std::path::Path) -> Result<()> {
+        let edited = std::fs::read_to_string(path)?;
+        let _ = std::fs::remove_file(path);
+
+        let patch: String = edited
+            .lines()
+            .filter(|l| !l.starts_with('#'))
+            .map(|l| format!("{}\n", l))
+            .collect();
+        let patch = git::patch::recount(&patch);
+        if !patch.lines().any(|l| l.starts_with("@@")) {
+            return Ok(()); // Aborted
+        }
+
+        let unstage = self.diff_mode == git::DiffMode::Index;
+        git::patch::apply_edited_patch(&patch, unstage)?;
+        let paths = git::patch::parse_diff(&patch)
+            .0
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+
+        let action = if unstage {
+            StageAction::UnstageHunk(patch)
+        } else {
+            StageAction::StageHunk(patch)
+        };
+        self.history.push_action(paths, action);
+
+        self.diff_untracked = false;
+        self.refresh()?;
+        self.reload_diff()
+    }
+
+    /// Nodes covered by the visual selection, or just the cursor node.
+    fn selected_nodes(&self) -> Vec<&FlatNode> {
         let (nodes, state) = match self.layout {
             AppLayout::Unified | AppLayout::Compact | AppLayout::EasterEgg => {
                 (&self.unified_nodes, &self.unified_state)
@@ -958,18 +2613,25 @@ impl App {
                 Focus::Unstaged => (&self.unstaged_nodes, &self.unstaged_state),
             },
         };
-        // Simplified: use self.filter_nodes logic...
-        // Actually, we are already in Diff View, so we must have a selected node.
-        // We can just re-use the logic from show_diff.
-        // BUT, retrieving it cleanly is better.
-
         let filtered = Self::filter_nodes(nodes, &self.search_query);
-        if let Some(i) = state.selected() {
-            filtered.get(i).copied()
+
+        if self.is_visual_mode {
+            match self.get_visual_range() {
+                Some((start, end)) => filtered
+                    .get(start..=end)
+                    .map(|s| s.to_vec())
+                    .unwrap_or_default(),
+                None => Vec::new(),
+            }
         } else {
-            None
+            state
+                .selected()
+                .and_then(|i| filtered.get(i).copied())
+                .into_iter()
+                .collect()
         }
     }
+
     pub fn next_diff_match(&mut self) {
         if self.diff_matches.is_empty() {
             return;
@@ -995,9 +2657,37 @@ impl App {
     }
 
     fn jump_to_diff_match(&mut self) {
-        if let Some(i) = self.current_diff_match {
-            if let Some(&line_idx) = self.diff_matches.get(i) {
-                self.diff_scroll = line_idx as u16;
+        let Some(&line_idx) = self
+            .current_diff_match
+            .and_then(|i| self.diff_matches.get(i))
+        else {
+            return;
+        };
+        let line = match self.file_view.as_mut() {
+            Some(view) if self.view_mode == ViewMode::File => {
+                view.scroll = line_idx;
+                view.lines.get(line_idx).cloned().unwrap_or_default()
+            }
+            _ => {
+                self.diff_scroll = line_idx;
+                self.diff_content
+                    .lines()
+                    .nth(line_idx)
+                    .unwrap_or("")
+                    .to_string()
+            }
+        };
+
+        // Without wrapping, scroll sideways when the match is off screen
+        if self.diff_wrap {
+            return;
+        }
+        let clean_line = strip_ansi_codes(&line).to_lowercase();
+        if let Some(idx) = clean_line.find(&self.diff_search_query.to_lowercase()) {
+            let column = clean_line[..idx].width();
+            let end = column + self.diff_search_query.width();
+            if column < self.diff_hscroll || end > self.diff_hscroll + self.diff_text_width {
+                self.diff_hscroll = column.saturating_sub(8);
             }
         }
     }
@@ -1141,6 +2831,7 @@ impl App {
 }
 
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod tests {
     use super::*;
 
@@ -1157,6 +2848,10 @@ mod tests {
         assert_eq!(mode, FilterMode::Staged);
         assert_eq!(mode.as_str(), "Staged");
 
+        let mode = mode.next();
+        assert_eq!(mode, FilterMode::Ignored);
+        assert_eq!(mode.as_str(), "Ignored");
+
         let mode = mode.next();
         assert_eq!(mode, FilterMode::All);
     }
@@ -0,0 +1,49 @@
+use anyhow::Result;
+
+use super::app::SavedDiff;
+use super::list_cursor::ListCursor;
+use crate::git::{self, blame::BlameLine};
+
+/// `git blame` of a file, one selectable line at a time.
+pub struct BlameView {
+    pub path: String,
+    pub lines: Vec<BlameLine>,
+    pub cursor: ListCursor,
+    oldest: i64,
+    newest: i64,
+    pub previous_diff: Option<SavedDiff>, // Diff to restore when going back to it
+}
+
+impl BlameView {
+    pub fn open(path: &str) -> Result<Self> {
+        let lines = git::blame::blame(path)?;
+        let times = lines.iter().filter(|l| !l.is_uncommitted()).map(|l| l.time);
+        let oldest = times.clone().min().unwrap_or(0);
+        let newest = times.max().unwrap_or(0);
+        Ok(BlameView {
+            path: path.to_string(),
+            lines,
+            cursor: ListCursor::default(),
+            oldest,
+            newest,
+            previous_diff: None,
+        })
+    }
+
+    pub fn move_by(&mut self, amount: isize) {
+        self.cursor.move_by(amount, self.lines.len());
+    }
+
+    pub fn selected_line(&self) -> Option<&BlameLine> {
+        self.lines.get(self.cursor.selected)
+    }
+
+    /// Where the line's commit falls between the oldest (0.0) and newest
+    /// (1.0) commit of the file.
+    pub fn age(&self, line: &BlameLine) -> f64 {
+        if line.is_uncommitted() || self.newest == self.oldest {
+            return 1.0;
+        }
+        (line.time - self.oldest) as f64 / (self.newest - self.oldest) as f64
+    }
+}
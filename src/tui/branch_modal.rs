@@ -0,0 +1,78 @@
+use anyhow::Result;
+use ratatui::widgets::ListState;
+
+use crate::git::branch::{self, Branch};
+
+/// A branch name being typed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BranchInput {
+    Create { start: String }, // Starting point: HEAD or the selected branch
+    Rename { old: String },
+    Upstream { branch: String },
+}
+
+/// A yes/no question asked before acting on the selected branch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BranchConfirm {
+    Checkout,                              // The worktree has local changes
+    Delete { name: String, merged: bool }, // Unmerged branches are force-deleted
+}
+
+/// Lists branches and holds whatever is being typed or confirmed about them.
+pub struct BranchModal {
+    pub branches: Vec<Branch>,
+    pub state: ListState,
+    pub input: Option<(BranchInput, String)>,
+    pub confirm: Option<BranchConfirm>,
+}
+
+impl BranchModal {
+    pub fn open() -> Result<Self> {
+        let mut modal = BranchModal {
+            branches: Vec::new(),
+            state: ListState::default(),
+            input: None,
+            confirm: None,
+        };
+        modal.reload(None)?;
+        Ok(modal)
+    }
+
+    /// Reads the branches again, selecting `name` if given, or else the
+    /// current branch.
+    pub fn reload(&mut self, name: Option<&str>) -> Result<()> {
+        self.branches = branch::list_branches()?;
+        let selected = self
+            .branches
+            .iter()
+            .position(|b| match name {
+                Some(name) => !b.is_remote && b.name == name,
+                None => b.is_head,
+            })
+            .or(Some(0).filter(|_| !self.branches.is_empty()));
+        self.state.select(selected);
+        Ok(())
+    }
+
+    pub fn move_selection(&mut self, down: bool) {
+        if self.branches.is_empty() {
+            return;
+        }
+        let len = self.branches.len();
+        let i = self.state.selected().unwrap_or(0);
+        self.state.select(Some(if down {
+            (i + 1) % len
+        } else {
+            (i + len - 1) % len
+        }));
+    }
+
+    pub fn selected(&self) -> Option<&Branch> {
+        self.state.selected().and_then(|i| self.branches.get(i))
+    }
+
+    /// Whether a local branch is called `name`.
+    pub fn has_local(&self, name: &str) -> bool {
+        self.branches.iter().any(|b| !b.is_remote && b.name == name)
+    }
+}
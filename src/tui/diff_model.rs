@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::app::DiffLayout;
+use super::side_by_side::{self, Row};
+
+/// Diffs with more lines than this wait for a key press before being shown.
+pub const LARGE_DIFF_LINES: usize = 20_000;
+
+/// Everything the diff view derives from the diff text, worked out once per
+/// diff (and per layout) instead of on every frame. Rendering then only
+/// touches the rows on screen.
+#[derive(Default)]
+pub struct DiffModel {
+    built: bool, // Cleared by `invalidate` when the diff text changes
+    source: String,
+    lines: Vec<Range<usize>>, // Byte range of each line in `source`
+    pairs: Vec<Row>,
+    folds: Vec<Range<usize>>,
+    pub numbers: Vec<(Option<usize>, Option<usize>)>,
+    pub number_width: usize,
+    pub partners: HashMap<usize, usize>,
+    view: Option<(DiffLayout, bool)>,
+    pub rows: Vec<Row>,
+}
+
+impl DiffModel {
+    /// Marks the model out of date, so the next `update` reads the diff text
+    /// again.
+    pub fn invalidate(&mut self) {
+        self.built = false;
+    }
+
+    /// Rebuilds what changed since the last call: everything after
+    /// `invalidate`, only the rows for another layout or fold setting.
+    pub fn update(&mut self, diff_content: &str, layout: DiffLayout, fold_unchanged: bool) {
+        if !self.built {
+            self.built = true;
+            self.source = diff_content.to_string();
+            let mut start = 0;
+            self.lines = diff_content
+                .split_inclusive('\n')
+                .map(|line| {
+                    let range = start..start + line.trim_end_matches('\n').len();
+                    start += line.len();
+                    range
+                })
+                .collect();
+            self.pairs = side_by_side::pair_lines(diff_content);
+            self.folds = side_by_side::unchanged_runs(diff_content);
+            self.numbers = side_by_side::line_numbers(diff_content);
+            self.number_width = self
+                .numbers
+                .iter()
+                .filter_map(|&(old, new)| old.max(new))
+                .max()
+                .unwrap_or(0)
+                .to_string()
+                .len();
+            self.partners = line_partners(&self.pairs);
+            self.view = None;
+        }
+
+        if self.view == Some((layout, fold_unchanged)) {
+            return;
+        }
+        self.view = Some((layout, fold_unchanged));
+        let rows = match layout {
+            DiffLayout::SideBySide => self.pairs.clone(),
+            DiffLayout::Unified => (0..self.lines.len()).map(Row::Full).collect(),
+        };
+        self.rows = if fold_unchanged {
+            side_by_side::fold_rows(rows, &self.folds)
+        } else {
+            rows
+        };
+    }
+
+    pub fn line(&self, raw: usize) -> &str {
+        self.lines
+            .get(raw)
+            .map(|r| &self.source[r.clone()])
+            .unwrap_or("")
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+}
+
+/// Maps every removed line that sits across from an added line in the
+/// side-by-side pairing to that line, and the other way round.
+fn line_partners(rows: &[Row]) -> HashMap<usize, usize> {
+    let mut partners = HashMap::new();
+    for row in rows {
+        if let Row::Split {
+            old: Some(old),
+            new: Some(new),
+        } = row
+        {
+            if old.raw != new.raw {
+                partners.insert(old.raw, new.raw);
+                partners.insert(new.raw, old.raw);
+            }
+        }
+    }
+    partners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/f b/f\n@@ -9,2 +9,2 @@\n-a\n+b\n c\n";
+
+    #[test]
+    fn test_model_lines_and_rows() {
+        let mut model = DiffModel::default();
+        model.update(DIFF, DiffLayout::Unified, false);
+        assert_eq!(model.len(), 5);
+        assert_eq!(model.line(2), "-a");
+        assert_eq!(model.line(9), "");
+        assert_eq!(model.rows.len(), 5);
+        assert_eq!(model.number_width, 2);
+        assert_eq!(model.partners.get(&2), Some(&3));
+
+        // Same text, other layout: only the rows change
+        model.update(DIFF, DiffLayout::SideBySide, false);
+        assert_eq!(model.rows.len(), 4);
+
+        // New text is only read once the model is invalidated
+        model.update("+x\n", DiffLayout::SideBySide, false);
+        assert_eq!(model.len(), 5);
+        model.invalidate();
+        model.update("+x\n", DiffLayout::SideBySide, false);
+        assert_eq!(model.len(), 1);
+        assert_eq!(model.line(0), "+x");
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::{Context, Result};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Output of `twig.diff.tool` for the diff on screen, re-run only when the
+/// diff or the available width changes.
+#[derive(Default)]
+pub struct DiffToolCache {
+    source: String,
+    width: u16,
+    pub output: Option<Result<Vec<Line<'static>>, String>>,
+}
+
+impl DiffToolCache {
+    pub fn update(&mut self, command: &str, diff_content: &str, width: u16) {
+        if self.output.is_some() && self.source == diff_content && self.width == width {
+            return;
+        }
+        self.source = diff_content.to_string();
+        self.width = width;
+        self.output = Some(
+            run(command, diff_content, width)
+                .map(|out| parse_ansi(&out))
+                .map_err(|e| e.to_string()),
+        );
+    }
+}
+
+/// Pipes `diff_content` through `command` (run by `sh -c`, with `COLUMNS` set
+/// to the width of the diff view) and returns what it prints.
+fn run(command: &str, diff_content: &str, width: u16) -> Result<String> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .env("COLUMNS", width.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", command))?;
+
+    // Written from another thread so a tool that prints as it reads can't
+    // fill its stdout pipe while we are still blocked on its stdin
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Failed to open the tool's stdin")?;
+    let input = diff_content.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output()?;
+    let written = writer
+        .join()
+        .map_err(|_| anyhow::anyhow!("Writing to {} panicked", command))?;
+    if !output.status.success() {
+        let err = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("{} failed: {}", command, err.trim());
+    }
+    match written {
+        // A tool that succeeds without reading everything has what it needs
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+        result => result.with_context(|| format!("Failed to write the diff to {}", command))?,
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Turns text with ANSI SGR escapes into styled lines. Other escape sequences
+/// (cursor movement, OSC hyperlinks) are dropped.
+pub fn parse_ansi(text: &str) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut style = Style::default();
+
+    for raw_line in text.lines() {
+        let mut spans = Vec::new();
+        let mut current = String::new();
+        let mut chars = raw_line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '\x1b' {
+                current.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut terminator = None;
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            terminator = Some(c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                    if terminator == Some('m') {
+                        if !current.is_empty() {
+                            spans.push(Span::styled(std::mem::take(&mut current), style));
+                        }
+                        style = apply_sgr(style, &params);
+                    }
+                }
+                Some(']') => {
+                    // OSC: runs until BEL or ESC \
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !current.is_empty() {
+            spans.push(Span::styled(current, style));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let codes: Vec<u16> = if params.is_empty() {
+        vec![0]
+    } else {
+        params.split(';').map(|p| p.parse().unwrap_or(0)).collect()
+    };
+
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 => style = Style::default(),
+            1 => style = style.add_modifier(Modifier::BOLD),
+            2 => style = style.add_modifier(Modifier::DIM),
+            3 => style = style.add_modifier(Modifier::ITALIC),
+            4 => style = style.add_modifier(Modifier::UNDERLINED),
+            7 => style = style.add_modifier(Modifier::REVERSED),
+            9 => style = style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style = style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style = style.remove_modifier(Modifier::ITALIC),
+            24 => style = style.remove_modifier(Modifier::UNDERLINED),
+            27 => style = style.remove_modifier(Modifier::REVERSED),
+            29 => style = style.remove_modifier(Modifier::CROSSED_OUT),
+            c @ 30..=37 => style = style.fg(basic_color(c - 30)),
+            c @ 90..=97 => style = style.fg(bright_color(c - 90)),
+            c @ 40..=47 => style = style.bg(basic_color(c - 40)),
+            c @ 100..=107 => style = style.bg(bright_color(c - 100)),
+            39 => style.fg = None,
+            49 => style.bg = None,
+            c @ (38 | 48) => {
+                let color = match codes.get(i + 1) {
+                    Some(5) => {
+                        let color = codes.get(i + 2).map(|&n| Color::Indexed(n as u8));
+                        i += 2;
+                        color
+                    }
+                    Some(2) => {
+                        let rgb = (codes.get(i + 2), codes.get(i + 3), codes.get(i + 4));
+                        i += 4;
+                        match rgb {
+                            (Some(&r), Some(&g), Some(&b)) => {
+                                Some(Color::Rgb(r as u8, g as u8, b as u8))
+                            }
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(color) = color {
+                    style = if c == 38 {
+                        style.fg(color)
+                    } else {
+                        style.bg(color)
+                    };
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    style
+}
+
+fn basic_color(n: u16) -> Color {
+    match n {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        _ => Color::Gray,
+    }
+}
+
+fn bright_color(n: u16) -> Color {
+    match n {
+        0 => Color::DarkGray,
+        1 => Color::LightRed,
+        2 => Color::LightGreen,
+        3 => Color::LightYellow,
+        4 => Color::LightBlue,
+        5 => Color::LightMagenta,
+        6 => Color::LightCyan,
+        _ => Color::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ansi_colors() {
+        let lines = parse_ansi("\x1b[31m-old\x1b[0m\n\x1b[1;38;2;10;20;30m+new\x1b[m plain");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].spans[0].content, "-old");
+        assert_eq!(lines[0].spans[0].style.fg, Some(Color::Red));
+
+        let new = &lines[1].spans[0];
+        assert_eq!(new.content, "+new");
+        assert_eq!(new.style.fg, Some(Color::Rgb(10, 20, 30)));
+        assert!(new.style.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(lines[1].spans[1].style, Style::default());
+    }
+
+    #[test]
+    fn test_parse_ansi_strips_other_escapes() {
+        let lines = parse_ansi("\x1b]8;;file:///a\x1b\\a.rs\x1b]8;;\x1b\\\x1b[K!");
+        let text: String = lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(text, "a.rs!");
+    }
+
+    #[test]
+    fn test_parse_ansi_style_carries_over_lines() {
+        let lines = parse_ansi("\x1b[32ma\nb\x1b[0m");
+        assert_eq!(lines[1].spans[0].style.fg, Some(Color::Green));
+    }
+
+    #[test]
+    fn test_run_tool() {
+        let out = run("tr a-z A-Z", "diff\n", 80).unwrap();
+        assert_eq!(out, "DIFF\n");
+        assert!(run("exit 3", "", 80).is_err());
+
+        // More than a pipe buffer each way
+        let big = "x".repeat(1 << 20);
+        assert_eq!(run("cat", &big, 80).unwrap().len(), big.len());
+        assert!(run("head -c 1", &big, 80).is_ok());
+    }
+}
@@ -1,10 +1,17 @@
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::{
+    event::{self, Event, KeyCode},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io::{self, Stdout};
+use std::process::Command;
 
 use super::app::{App, AppLayout, ViewMode};
+use super::log_view::SearchResult;
 use super::ui::ui;
 use crate::config::Action;
+use crate::git;
 
 pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App) -> io::Result<()> {
     loop {
@@ -12,6 +19,8 @@ pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App)
 
         if event::poll(std::time::Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
+                app.status_message = None;
+
                 if app.show_commit_dialog {
                     match key.code {
                         KeyCode::Char(c) => {
@@ -31,6 +40,31 @@ pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App)
                         }
                         _ => {}
                     }
+                } else if app.pending_discard.is_some() {
+                    match key.code {
+                        KeyCode::Char('y') | KeyCode::Enter => {
+                            if let Err(e) = app.confirm_discard() {
+                                app.status_message = Some(e.to_string());
+                            }
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc | KeyCode::Char('q') => {
+                            app.cancel_discard();
+                        }
+                        _ => {}
+                    }
+                } else if app.show_ignore_dialog {
+                    match key.code {
+                        KeyCode::Char('j') | KeyCode::Down => app.move_ignore_selection(true),
+                        KeyCode::Char('k') | KeyCode::Up => app.move_ignore_selection(false),
+                        KeyCode::Tab => app.cycle_ignore_target(),
+                        KeyCode::Esc | KeyCode::Char('q') => app.close_ignore_dialog(),
+                        KeyCode::Enter => {
+                            let _ = app.confirm_ignore();
+                        }
+                        _ => {}
+                    }
+                } else if app.branch_modal.is_some() {
+                    handle_branch_key(app, key.code);
                 } else if app.is_typing_search {
                     match key.code {
                         KeyCode::Char(c) => {
@@ -51,6 +85,26 @@ pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App)
                         }
                         _ => {}
                     }
+                } else if app.view_mode == ViewMode::Log
+                    && app.log_view.as_ref().is_some_and(|v| v.is_typing_search)
+                {
+                    if let Some(view) = app.log_view.as_mut() {
+                        match key.code {
+                            KeyCode::Char(c) => view.search_query.push(c),
+                            KeyCode::Backspace => {
+                                view.search_query.pop();
+                            }
+                            KeyCode::Esc => {
+                                view.is_typing_search = false;
+                                view.search_query.clear();
+                            }
+                            KeyCode::Enter => {
+                                view.is_typing_search = false;
+                                search_log(app, true);
+                            }
+                            _ => {}
+                        }
+                    }
                 } else if app.is_diff_search {
                     match key.code {
                         KeyCode::Char(c) => {
@@ -81,6 +135,8 @@ pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App)
                             match (pending, c) {
                                 ('g', 'g') => action = Some(Action::JumpToTop),
                                 ('z', 'z') => action = Some(Action::CenterView),
+                                ('z', 'h') => action = Some(Action::ScrollLeft),
+                                ('z', 'l') => action = Some(Action::ScrollRight),
                                 _ => {}
                             }
                             app.pending_key = None;
@@ -181,6 +237,11 @@ pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App)
                     match app.view_mode {
                         ViewMode::Tree => {
                             if let Some(action) = action {
+                                if app.viewed_commit.is_some()
+                                    && handle_commit_tree_action(app, &action)
+                                {
+                                    continue;
+                                }
                                 match action {
                                     Action::Quit => return Ok(()),
                                     Action::Search => {
@@ -243,6 +304,7 @@ pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App)
                                     }
                                     Action::JumpToTop => app.jump_to_top(),
                                     Action::JumpToBottom => app.jump_to_bottom(),
+                                    Action::ScrollLeft | Action::ScrollRight => {}
                                     Action::CenterView => {
                                         // CenterView is handled in UI by ensuring ListState offset
                                         // But for now, we don't have a direct way to force offset in ratatui List
@@ -267,15 +329,49 @@ pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App)
                                     Action::Redo => {
                                         let _ = app.redo_staging();
                                     }
+                                    Action::Branches => {
+                                        if let Err(e) = app.open_branches() {
+                                            app.status_message = Some(e.to_string());
+                                        }
+                                    }
                                     Action::ToggleWorktrees => {
                                         let _ = app.toggle_worktrees();
                                     }
                                     Action::Commit => {
                                         app.open_commit_dialog();
                                     }
+                                    Action::Ignore => {
+                                        app.open_ignore_dialog();
+                                    }
+                                    Action::Discard => {
+                                        if let Err(e) = app.request_discard() {
+                                            app.status_message = Some(e.to_string());
+                                        }
+                                    }
+                                    Action::Review => {
+                                        if let Err(e) = app.open_review() {
+                                            app.status_message = Some(e.to_string());
+                                        }
+                                    }
+                                    Action::FileView => open_file_view(app),
+                                    Action::Blame => open_blame(app),
+                                    Action::History => open_history(app),
+                                    Action::Log => open_log(app),
                                 }
                             }
                         }
+                        ViewMode::Diff if app.line_mode => match key.code {
+                            KeyCode::Char('j') | KeyCode::Down => app.move_line_cursor(true),
+                            KeyCode::Char('k') | KeyCode::Up => app.move_line_cursor(false),
+                            KeyCode::Char(' ') => app.toggle_line_selection(),
+                            KeyCode::Enter | KeyCode::Char('s') => {
+                                if let Err(e) = app.stage_lines() {
+                                    app.status_message = Some(e.to_string());
+                                }
+                            }
+                            KeyCode::Esc | KeyCode::Char('q') => app.exit_line_mode(),
+                            _ => {}
+                        },
                         ViewMode::Diff => {
                             if let Some(action) = action {
                                 match action {
@@ -301,6 +397,10 @@ pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App)
                                             app.scroll_diff(-1);
                                         }
                                     }
+                                    Action::Collapse => app.scroll_diff_horizontal(-8),
+                                    Action::Expand => app.scroll_diff_horizontal(8),
+                                    Action::ScrollLeft => app.scroll_diff_horizontal(-1),
+                                    Action::ScrollRight => app.scroll_diff_horizontal(1),
                                     Action::PageUp => app.scroll_diff(-15),
                                     Action::PageDown => app.scroll_diff(15),
                                     Action::JumpToTop => app.scroll_diff(-1000), // Jump to top of diff
@@ -310,29 +410,422 @@ pub fn run_app(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App)
                                     }
                                     Action::Stage => {
                                         if app.patch_mode {
-                                            let _ = app.stage_hunk();
+                                            if let Err(e) = app.stage_hunk() {
+                                                app.status_message = Some(e.to_string());
+                                            }
                                         }
                                     }
-                                    _ => match key.code {
-                                        KeyCode::Char('n') => app.next_diff_match(),
-                                        KeyCode::Char('N') => app.prev_diff_match(),
-                                        KeyCode::Char('p') => app.toggle_patch_mode(),
-                                        _ => {}
-                                    },
+                                    Action::Discard => {
+                                        if let Err(e) = app.request_discard() {
+                                            app.status_message = Some(e.to_string());
+                                        }
+                                    }
+                                    Action::Undo => {
+                                        let _ = app.undo_staging();
+                                    }
+                                    Action::Redo => {
+                                        let _ = app.redo_staging();
+                                    }
+                                    Action::Layout => {
+                                        if let Err(e) = app.toggle_diff_layout() {
+                                            app.status_message =
+                                                Some(format!("Layout not saved: {}", e));
+                                        }
+                                    }
+                                    Action::VisualMode => {
+                                        if app.patch_mode {
+                                            app.enter_line_mode();
+                                        }
+                                    }
+                                    Action::FileView => open_file_view(app),
+                                    Action::Blame => open_blame(app),
+                                    Action::History => open_history(app),
+                                    Action::Log => open_log(app),
+                                    _ => handle_diff_key(terminal, app, key.code)?,
                                 }
                             } else {
                                 // Handle keys not in mappings (like n/N if not mapped)
-                                match key.code {
-                                    KeyCode::Char('n') => app.next_diff_match(),
-                                    KeyCode::Char('N') => app.prev_diff_match(),
-                                    KeyCode::Char('p') => app.toggle_patch_mode(),
-                                    _ => {}
-                                }
+                                handle_diff_key(terminal, app, key.code)?;
                             }
                         }
+                        ViewMode::Blame => match action {
+                            Some(Action::Quit | Action::Back | Action::Blame) => {
+                                if let Err(e) = app.close_blame() {
+                                    app.status_message = Some(e.to_string());
+                                }
+                            }
+                            Some(Action::MoveDown) => move_blame(app, 1),
+                            Some(Action::MoveUp) => move_blame(app, -1),
+                            Some(Action::PageDown) => move_blame(app, 15),
+                            Some(Action::PageUp) => move_blame(app, -15),
+                            Some(Action::JumpToTop) => move_blame(app, isize::MIN),
+                            Some(Action::JumpToBottom) => move_blame(app, isize::MAX),
+                            Some(Action::Collapse) => app.scroll_diff_horizontal(-8),
+                            Some(Action::Expand) => app.scroll_diff_horizontal(8),
+                            Some(Action::ScrollLeft) => app.scroll_diff_horizontal(-1),
+                            Some(Action::ScrollRight) => app.scroll_diff_horizontal(1),
+                            Some(Action::Diff) => {
+                                if let Err(e) = app.show_blame_commit() {
+                                    app.status_message = Some(e.to_string());
+                                }
+                            }
+                            _ => {
+                                if key.code == KeyCode::Char('W') {
+                                    app.toggle_diff_wrap();
+                                }
+                            }
+                        },
+                        ViewMode::Log => match action {
+                            Some(Action::Quit | Action::Back | Action::Log) => {
+                                if let Err(e) = app.close_log() {
+                                    app.status_message = Some(e.to_string());
+                                }
+                            }
+                            Some(Action::MoveDown) => move_log(app, 1),
+                            Some(Action::MoveUp) => move_log(app, -1),
+                            Some(Action::PageDown) => move_log(app, 15),
+                            Some(Action::PageUp) => move_log(app, -15),
+                            Some(Action::JumpToTop) => move_log(app, isize::MIN),
+                            Some(Action::JumpToBottom) => move_log(app, isize::MAX),
+                            Some(Action::Search) => {
+                                if let Some(view) = app.log_view.as_mut() {
+                                    view.is_typing_search = true;
+                                    view.search_query.clear();
+                                }
+                            }
+                            Some(Action::Diff) => {
+                                if let Err(e) = app.open_commit_tree() {
+                                    app.status_message = Some(e.to_string());
+                                }
+                            }
+                            _ => match key.code {
+                                KeyCode::Char('n') => search_log(app, true),
+                                KeyCode::Char('N') => search_log(app, false),
+                                _ => {}
+                            },
+                        },
+                        ViewMode::History => match action {
+                            Some(Action::Quit | Action::Back | Action::History) => {
+                                if let Err(e) = app.close_history() {
+                                    app.status_message = Some(e.to_string());
+                                }
+                            }
+                            Some(Action::MoveDown) => move_history(app, 1),
+                            Some(Action::MoveUp) => move_history(app, -1),
+                            Some(Action::PageDown) => move_history(app, 15),
+                            Some(Action::PageUp) => move_history(app, -15),
+                            Some(Action::JumpToTop) => move_history(app, isize::MIN),
+                            Some(Action::JumpToBottom) => move_history(app, isize::MAX),
+                            Some(Action::Diff) => {
+                                if let Err(e) = app.show_history_commit() {
+                                    app.status_message = Some(e.to_string());
+                                }
+                            }
+                            _ => {}
+                        },
+                        ViewMode::File => match action {
+                            Some(Action::Quit | Action::Back | Action::FileView) => {
+                                if let Err(e) = app.close_file_view() {
+                                    app.status_message = Some(e.to_string());
+                                }
+                            }
+                            Some(Action::MoveDown) => scroll_file_view(app, 1),
+                            Some(Action::MoveUp) => scroll_file_view(app, -1),
+                            Some(Action::PageDown) => scroll_file_view(app, 15),
+                            Some(Action::PageUp) => scroll_file_view(app, -15),
+                            Some(Action::JumpToTop) => scroll_file_view(app, isize::MIN),
+                            Some(Action::JumpToBottom) => scroll_file_view(app, isize::MAX),
+                            Some(Action::Collapse) => app.scroll_diff_horizontal(-8),
+                            Some(Action::Expand) => app.scroll_diff_horizontal(8),
+                            Some(Action::ScrollLeft) => app.scroll_diff_horizontal(-1),
+                            Some(Action::ScrollRight) => app.scroll_diff_horizontal(1),
+                            Some(Action::Search) => {
+                                app.is_diff_search = true;
+                                app.diff_search_query.clear();
+                            }
+                            Some(Action::Stage) => {
+                                if let Err(e) = app.stage_file_change() {
+                                    app.status_message = Some(format!("Cannot stage: {}", e));
+                                }
+                            }
+                            Some(Action::Undo) => {
+                                let _ = app.undo_staging();
+                            }
+                            Some(Action::Redo) => {
+                                let _ = app.redo_staging();
+                            }
+                            _ => match key.code {
+                                KeyCode::Char('n') => app.next_diff_match(),
+                                KeyCode::Char('N') => app.prev_diff_match(),
+                                KeyCode::Char(']') | KeyCode::Char('[') => {
+                                    if let Some(view) = app.file_view.as_mut() {
+                                        view.jump_to_change(key.code == KeyCode::Char(']'));
+                                    }
+                                }
+                                KeyCode::Char('m') => {
+                                    if let Err(e) = app.cycle_file_side() {
+                                        app.status_message = Some(e.to_string());
+                                    }
+                                }
+                                KeyCode::Char('W') => app.toggle_diff_wrap(),
+                                _ => {}
+                            },
+                        },
                     }
                 }
             }
         }
     }
 }
+
+fn handle_diff_key(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &mut App,
+    code: KeyCode,
+) -> io::Result<()> {
+    match code {
+        KeyCode::Char('n') => app.next_diff_match(),
+        KeyCode::Char('N') => app.prev_diff_match(),
+        KeyCode::Char('p') => app.toggle_patch_mode(),
+        KeyCode::Char('H') => {
+            if let Err(e) = app.toggle_syntax_highlight() {
+                app.status_message = Some(format!("Setting not saved: {}", e));
+            }
+        }
+        KeyCode::Char('m') => {
+            if let Err(e) = app.cycle_diff_mode() {
+                app.status_message = Some(e.to_string());
+            }
+        }
+        KeyCode::Char('w') => change_diff_options(app, |o| o.cycle_whitespace()),
+        KeyCode::Char('+') | KeyCode::Char('=') => {
+            change_diff_options(app, |o| o.change_context(1))
+        }
+        KeyCode::Char('-') => change_diff_options(app, |o| o.change_context(-1)),
+        KeyCode::Char('a') => change_diff_options(app, |o| o.cycle_algorithm()),
+        KeyCode::Char('M') => change_diff_options(app, |o| o.cycle_rename_threshold()),
+        KeyCode::Char('K') | KeyCode::Char('J') => {
+            if let Err(e) = app.expand_hunk_context(code == KeyCode::Char('K')) {
+                app.status_message = Some(format!("Cannot expand context: {}", e));
+            }
+        }
+        KeyCode::Char('F') => app.toggle_fold_unchanged(),
+        KeyCode::Char('L') => app.load_deferred_diff(),
+        KeyCode::Char('W') => app.toggle_diff_wrap(),
+        KeyCode::Tab => {
+            if let Err(e) = app.toggle_diff_fold() {
+                app.status_message = Some(e.to_string());
+            }
+        }
+        KeyCode::Char(']') if app.patch_mode => app.next_diff_file(),
+        KeyCode::Char('[') if app.patch_mode => app.prev_diff_file(),
+        KeyCode::Char('S') if app.patch_mode => app.split_hunk(),
+        KeyCode::Char('e') if app.patch_mode => edit_hunk(terminal, app)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+fn open_file_view(app: &mut App) {
+    if let Err(e) = app.open_file_view() {
+        app.status_message = Some(format!("Cannot open file: {}", e));
+    }
+}
+
+fn open_blame(app: &mut App) {
+    if let Err(e) = app.open_blame() {
+        app.status_message = Some(format!("Cannot blame: {}", e));
+    }
+}
+
+fn move_blame(app: &mut App, amount: isize) {
+    if let Some(view) = app.blame_view.as_mut() {
+        view.move_by(amount);
+    }
+}
+
+fn open_log(app: &mut App) {
+    if let Err(e) = app.open_log() {
+        app.status_message = Some(format!("Cannot show the log: {}", e));
+    }
+}
+
+fn move_log(app: &mut App, amount: isize) {
+    if let Some(view) = app.log_view.as_mut() {
+        if let Err(e) = view.move_by(amount) {
+            app.status_message = Some(e.to_string());
+        }
+    }
+}
+
+fn search_log(app: &mut App, forward: bool) {
+    let Some(view) = app.log_view.as_mut() else {
+        return;
+    };
+    match view.search(forward) {
+        Ok(SearchResult::Found) => {}
+        Ok(SearchResult::NotFound) if view.search_query.is_empty() => {}
+        Ok(SearchResult::NotFound) => {
+            app.status_message = Some(format!("No more commits match '{}'", view.search_query))
+        }
+        Ok(SearchResult::Stopped(searched)) => {
+            app.status_message = Some(format!(
+                "Searched {} commits without a match for '{}': press n to keep looking",
+                searched, view.search_query
+            ))
+        }
+        Err(e) => app.status_message = Some(e.to_string()),
+    }
+}
+
+/// Keys that act differently while the tree shows a commit's files: `q` goes
+/// back to the log, and anything that would change the worktree is refused.
+/// Returns whether the action was handled here.
+fn handle_commit_tree_action(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::Quit | Action::Log => {
+            if let Err(e) = app.close_commit_tree() {
+                app.status_message = Some(e.to_string());
+            }
+            true
+        }
+        Action::Stage
+        | Action::Filter
+        | Action::Layout
+        | Action::ToggleTree
+        | Action::Undo
+        | Action::Redo
+        | Action::ToggleWorktrees
+        | Action::Branches
+        | Action::Commit
+        | Action::Ignore
+        | Action::Discard
+        | Action::Review
+        | Action::FileView => {
+            app.status_message =
+                Some("Viewing a commit: press q to go back to the log".to_string());
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Keys of the branch modal: a name being typed or a question being answered
+/// take every key, otherwise they act on the selected branch.
+fn handle_branch_key(app: &mut App, code: KeyCode) {
+    let Some(modal) = app.branch_modal.as_mut() else {
+        return;
+    };
+    let result = if modal.confirm.is_some() {
+        match code {
+            KeyCode::Char('y') | KeyCode::Enter => app.answer_branch_confirm(true),
+            KeyCode::Char('n') | KeyCode::Char('q') | KeyCode::Esc => {
+                app.answer_branch_confirm(false)
+            }
+            _ => Ok(()),
+        }
+    } else if let Some((_, text)) = modal.input.as_mut() {
+        match code {
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Esc => modal.input = None,
+            _ => {}
+        }
+        if code == KeyCode::Enter {
+            app.submit_branch_input()
+        } else {
+            Ok(())
+        }
+    } else {
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => {
+                modal.move_selection(true);
+                Ok(())
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                modal.move_selection(false);
+                Ok(())
+            }
+            KeyCode::Enter => app.checkout_branch(),
+            KeyCode::Char(c @ ('n' | 'N' | 'r' | 'u')) => {
+                app.start_branch_input(c);
+                Ok(())
+            }
+            KeyCode::Char('d') => app.request_branch_delete(),
+            KeyCode::Char('q') | KeyCode::Char('B') | KeyCode::Esc => {
+                app.branch_modal = None;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    };
+    if let Err(e) = result {
+        app.status_message = Some(e.to_string());
+    }
+}
+
+fn open_history(app: &mut App) {
+    if let Err(e) = app.open_history() {
+        app.status_message = Some(format!("Cannot show history: {}", e));
+    }
+}
+
+fn move_history(app: &mut App, amount: isize) {
+    if let Some(view) = app.history_view.as_mut() {
+        view.move_by(amount);
+    }
+}
+
+fn scroll_file_view(app: &mut App, amount: isize) {
+    if let Some(view) = app.file_view.as_mut() {
+        view.scroll_by(amount);
+    }
+}
+
+fn change_diff_options(app: &mut App, change: impl FnOnce(&mut git::DiffOptions)) {
+    if let Err(e) = app.change_diff_options(change) {
+        app.status_message = Some(e.to_string());
+    }
+}
+
+/// Opens the selected hunk in git's editor, handing the terminal over while it
+/// runs.
+fn edit_hunk(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App) -> io::Result<()> {
+    let path = match app.prepare_hunk_edit() {
+        Ok(Some(path)) => path,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            app.status_message = Some(format!("Cannot edit hunk: {}", e));
+            return Ok(());
+        }
+    };
+
+    let editor = git::get_editor();
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    // The editor may carry arguments (`code -w`), so the shell splits it
+    let status = Command::new("sh")
+        .args(["-c", &format!("{} \"$1\"", editor), &editor])
+        .arg(&path)
+        .status();
+
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+    terminal.clear()?;
+
+    match status {
+        Ok(s) if s.success() => {
+            if let Err(e) = app.finish_hunk_edit(&path) {
+                app.status_message = Some(format!("Edited hunk does not apply: {}", e));
+            }
+        }
+        Ok(_) => app.status_message = Some("Editor exited with error".to_string()),
+        Err(e) => app.status_message = Some(format!("Failed to launch {}: {}", editor, e)),
+    }
+    Ok(())
+}
@@ -0,0 +1,300 @@
+use anyhow::Result;
+
+use super::app::ViewMode;
+use crate::git::{self, patch::parse_hunk_header, DiffFile, DiffMode, Hunk};
+
+/// How a line of the shown file differs from the other side of its diff.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Marker {
+    Added,
+    Modified,
+    Removed, // On the old side the line itself goes; on the new side lines went just above
+}
+
+/// Which version of a file the file view shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileSide {
+    Worktree,
+    Index,
+    Head,
+}
+
+impl FileSide {
+    pub fn next(&self) -> Self {
+        match self {
+            FileSide::Worktree => FileSide::Index,
+            FileSide::Index => FileSide::Head,
+            FileSide::Head => FileSide::Worktree,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileSide::Worktree => "Worktree",
+            FileSide::Index => "Index",
+            FileSide::Head => "HEAD",
+        }
+    }
+
+    /// The diff whose hunks mark this version up, and whether the version is
+    /// the old side of it. Worktree and index are marked against the version
+    /// they would be staged or unstaged into; HEAD against the worktree.
+    fn diff(&self) -> (DiffMode, bool) {
+        match self {
+            FileSide::Worktree => (DiffMode::Worktree, false),
+            FileSide::Index => (DiffMode::Index, false),
+            FileSide::Head => (DiffMode::Head, true),
+        }
+    }
+
+    /// Staging works on hunks of the worktree (stage) and index (unstage) diffs.
+    pub fn can_stage(&self) -> bool {
+        *self != FileSide::Head
+    }
+}
+
+/// A whole file with its changes marked in the gutter.
+pub struct FileView {
+    pub path: String,
+    pub side: FileSide,
+    pub untracked: bool,
+    pub lines: Vec<String>,
+    pub markers: Vec<Option<(Marker, usize)>>, // Marker and the hunk it comes from
+    pub change_starts: Vec<usize>,             // First marked line of each hunk
+    pub file: Option<DiffFile>,
+    pub hunks: Vec<Hunk>,
+    pub selected: Option<usize>, // Hunk staged by Space
+    pub scroll: usize,
+    pub return_to: ViewMode,
+}
+
+impl FileView {
+    pub fn open(path: &str, side: FileSide, untracked: bool, return_to: ViewMode) -> Result<Self> {
+        let mut view = FileView {
+            path: path.to_string(),
+            side,
+            untracked,
+            lines: Vec::new(),
+            markers: Vec::new(),
+            change_starts: Vec::new(),
+            file: None,
+            hunks: Vec::new(),
+            selected: None,
+            scroll: 0,
+            return_to,
+        };
+        view.reload()?;
+        Ok(view)
+    }
+
+    /// Re-reads the file and its diff, keeping scroll and the selected change.
+    pub fn reload(&mut self) -> Result<()> {
+        let content = match self.side {
+            FileSide::Worktree => git::read_worktree_file(&self.path)?,
+            FileSide::Index => git::show_file("", &self.path)?,
+            FileSide::Head => git::show_file("HEAD", &self.path)?,
+        };
+        self.lines = content.lines().map(|l| l.to_string()).collect();
+
+        // Markers and staging need the plain diff, whatever the diff view's options
+        let (mode, old_side) = self.side.diff();
+        let options = git::DiffOptions::default();
+        let diff = git::get_diff(&self.path, mode, self.untracked, &options)?;
+        let (markers, change_starts) = line_markers(&diff, old_side, self.lines.len());
+        let (files, hunks) = git::patch::parse_diff(&diff);
+        self.markers = markers;
+        self.change_starts = change_starts;
+        self.file = files.into_iter().next();
+        self.hunks = hunks;
+
+        self.selected = match self.selected {
+            _ if self.hunks.is_empty() => None,
+            Some(i) => Some(i.min(self.hunks.len() - 1)),
+            None => None,
+        };
+        self.scroll = self.scroll.min(self.lines.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Selects the next (or previous) change after the top of the view and
+    /// scrolls to it.
+    pub fn jump_to_change(&mut self, forward: bool) {
+        let current = self.selected.map(|i| self.change_starts[i]);
+        let target = if forward {
+            self.change_starts
+                .iter()
+                .position(|&start| current.map_or(start >= self.scroll, |c| start > c))
+        } else {
+            self.change_starts
+                .iter()
+                .rposition(|&start| current.map_or(start < self.scroll, |c| start < c))
+        };
+        if let Some(i) = target {
+            self.selected = Some(i);
+            self.scroll = self.change_starts[i].saturating_sub(3);
+        }
+    }
+
+    pub fn scroll_by(&mut self, amount: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(amount)
+            .min(self.lines.len().saturating_sub(1));
+    }
+}
+
+/// Gutter markers for a file with `line_count` lines, from its single-file
+/// `diff`. With `old_side` the file is the diff's old side. Each change block
+/// pairs removed with added lines as modifications; the rest are additions
+/// or removals. Also returns the first marked line of each hunk.
+pub fn line_markers(
+    diff: &str,
+    old_side: bool,
+    line_count: usize,
+) -> (Vec<Option<(Marker, usize)>>, Vec<usize>) {
+    let mut markers = vec![None; line_count];
+    let mut starts: Vec<usize> = Vec::new();
+    let mut hunk: Option<usize> = None;
+    let mut line = 0; // Index of the next shown-file line in the hunk
+    let (mut removed, mut added) = (0, 0);
+
+    let finish_block = |markers: &mut Vec<Option<(Marker, usize)>>,
+                        line: &mut usize,
+                        removed: &mut usize,
+                        added: &mut usize,
+                        hunk: usize| {
+        let (own, other) = if old_side {
+            (*removed, *added)
+        } else {
+            (*added, *removed)
+        };
+        let own_kind = if old_side {
+            Marker::Removed
+        } else {
+            Marker::Added
+        };
+        let mut first = None;
+        if own == 0 && other > 0 && line_count > 0 {
+            // Lines only exist on the other side: mark where they would be
+            let at = (*line).min(line_count - 1);
+            let other_kind = if old_side {
+                Marker::Added
+            } else {
+                Marker::Removed
+            };
+            markers[at].get_or_insert((other_kind, hunk));
+            first = Some(at);
+        }
+        for k in 0..own {
+            let kind = if k < other {
+                Marker::Modified
+            } else {
+                own_kind
+            };
+            if let Some(slot) = markers.get_mut(*line + k) {
+                *slot = Some((kind, hunk));
+                first.get_or_insert(*line + k);
+            }
+        }
+        *line += own;
+        *removed = 0;
+        *added = 0;
+        first
+    };
+
+    for l in diff.lines() {
+        if let Some((old_start, old_count, new_start, new_count, _)) = parse_hunk_header(l) {
+            if let Some(h) = hunk {
+                let first = finish_block(&mut markers, &mut line, &mut removed, &mut added, h);
+                record_start(&mut starts, h, first);
+            }
+            let (start, count) = if old_side {
+                (old_start, old_count)
+            } else {
+                (new_start, new_count)
+            };
+            // With a count of 0 the start is the line before the (empty) range
+            line = if count == 0 { start } else { start - 1 };
+            let h = starts.len();
+            starts.push(usize::MAX);
+            hunk = Some(h);
+            continue;
+        }
+        let Some(h) = hunk else {
+            continue;
+        };
+        match l.chars().next() {
+            Some('-') => removed += 1,
+            Some('+') => added += 1,
+            Some('\\') => {}
+            _ => {
+                let first = finish_block(&mut markers, &mut line, &mut removed, &mut added, h);
+                record_start(&mut starts, h, first);
+                line += 1;
+            }
+        }
+    }
+    if let Some(h) = hunk {
+        let first = finish_block(&mut markers, &mut line, &mut removed, &mut added, h);
+        record_start(&mut starts, h, first);
+    }
+
+    // A hunk with nothing to mark (e.g. an empty file) starts at the top
+    for start in starts.iter_mut().filter(|s| **s == usize::MAX) {
+        *start = 0;
+    }
+    (markers, starts)
+}
+
+fn record_start(starts: &mut [usize], hunk: usize, first: Option<usize>) {
+    if let Some(first) = first {
+        starts[hunk] = starts[hunk].min(first);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Line 2 modified, a line added after 4, line 7 removed
+    const DIFF: &str = "@@ -1,8 +1,8 @@\n a\n-b\n+B\n c\n d\n+new\n e\n f\n-g\n h\n";
+
+    #[test]
+    fn test_line_markers_new_side() {
+        let (markers, starts) = line_markers(DIFF, false, 8);
+        let kinds: Vec<Option<Marker>> = markers.iter().map(|m| m.map(|(k, _)| k)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                None,
+                Some(Marker::Modified),
+                None,
+                None,
+                Some(Marker::Added),
+                None,
+                None,
+                Some(Marker::Removed), // "h" follows the removed "g"
+            ]
+        );
+        assert_eq!(starts, vec![1]);
+    }
+
+    #[test]
+    fn test_line_markers_old_side() {
+        let (markers, _) = line_markers(DIFF, true, 8);
+        let kinds: Vec<Option<Marker>> = markers.iter().map(|m| m.map(|(k, _)| k)).collect();
+        assert_eq!(kinds[1], Some(Marker::Modified));
+        assert_eq!(kinds[4], Some(Marker::Added)); // "e" follows the added line
+        assert_eq!(kinds[6], Some(Marker::Removed));
+    }
+
+    #[test]
+    fn test_line_markers_hunks() {
+        let diff = "@@ -2,0 +3,2 @@\n+x\n+y\n@@ -9 +11 @@\n-p\n+q\n";
+        let (markers, starts) = line_markers(diff, false, 12);
+        assert_eq!(markers[2], Some((Marker::Added, 0)));
+        assert_eq!(markers[3], Some((Marker::Added, 0)));
+        assert_eq!(markers[10], Some((Marker::Modified, 1)));
+        assert_eq!(starts, vec![2, 10]);
+    }
+}
@@ -0,0 +1,120 @@
+use ratatui::style::Color;
+use std::ops::Range;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+
+use crate::git::patch::path_from_diff_header;
+use crate::icons;
+
+// Diffs longer than this are shown without syntax colors
+const MAX_LINES: usize = 20_000;
+
+/// Syntax colors of each diff line (byte ranges into the line without its
+/// `+`/`-`/` ` marker), computed once per diff content.
+#[derive(Default)]
+pub struct HighlightCache {
+    source: String,
+    lines: Vec<Vec<(Range<usize>, Color)>>,
+    assets: Option<(SyntaxSet, Theme)>, // Loaded on first use
+}
+
+impl HighlightCache {
+    pub fn line(&self, raw: usize) -> &[(Range<usize>, Color)] {
+        self.lines.get(raw).map(|l| l.as_slice()).unwrap_or(&[])
+    }
+
+    /// Re-highlights when `diff_content` differs from what the cache was built for.
+    pub fn update(&mut self, diff_content: &str) {
+        if self.source == diff_content {
+            return;
+        }
+        self.source = diff_content.to_string();
+        self.lines = vec![Vec::new(); diff_content.lines().count()];
+        if self.lines.len() > MAX_LINES {
+            return;
+        }
+
+        let (syntaxes, theme) = self.assets.get_or_insert_with(|| {
+            let mut themes = ThemeSet::load_defaults();
+            let theme = themes
+                .themes
+                .remove("base16-ocean.dark")
+                .unwrap_or_default();
+            (SyntaxSet::load_defaults_newlines(), theme)
+        });
+
+        let mut syntax = None;
+        // The old and new side are separate streams so multi-line constructs
+        // (strings, comments) stay consistent on each side
+        let mut sides: Option<(HighlightLines, HighlightLines)> = None;
+
+        for (raw, line) in diff_content.lines().enumerate() {
+            if let Some(path) = path_from_diff_header(line) {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                syntax = icons::get_syntax_extension(name)
+                    .and_then(|ext| syntaxes.find_syntax_by_extension(ext));
+                sides = None;
+                continue;
+            }
+            if line.starts_with("@@") {
+                sides =
+                    syntax.map(|s| (HighlightLines::new(s, theme), HighlightLines::new(s, theme)));
+                continue;
+            }
+            let Some((old, new)) = sides.as_mut() else {
+                continue;
+            };
+
+            let content = line.get(1..).unwrap_or("");
+            let with_newline = format!("{}\n", content);
+            let regions = match line.chars().next() {
+                Some('-') => old.highlight_line(&with_newline, syntaxes),
+                Some('+') => new.highlight_line(&with_newline, syntaxes),
+                Some(' ') => {
+                    let _ = old.highlight_line(&with_newline, syntaxes);
+                    new.highlight_line(&with_newline, syntaxes)
+                }
+                _ => continue,
+            };
+            let Ok(regions) = regions else {
+                continue;
+            };
+
+            let mut pos = 0;
+            for (style, text) in regions {
+                let end = (pos + text.len()).min(content.len());
+                if end > pos {
+                    let fg = style.foreground;
+                    self.lines[raw].push((pos..end, Color::Rgb(fg.r, fg.g, fg.b)));
+                }
+                pos += text.len();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_known_language() {
+        let diff = "diff --git a/m.rs b/m.rs\n--- a/m.rs\n+++ b/m.rs\n@@ -1 +1 @@\n-fn a() {}\n+fn b() {}\n";
+        let mut cache = HighlightCache::default();
+        cache.update(diff);
+
+        assert!(cache.line(2).is_empty()); // "+++" header
+        let added = cache.line(5);
+        assert!(added.len() > 1);
+        assert_eq!(added.last().unwrap().0.end, "fn b() {}".len());
+    }
+
+    #[test]
+    fn test_highlight_unknown_language() {
+        let diff = "diff --git a/x.xyz b/x.xyz\n@@ -1 +1 @@\n-a\n+b\n";
+        let mut cache = HighlightCache::default();
+        cache.update(diff);
+        assert!(cache.line(3).is_empty());
+    }
+}
@@ -1,7 +1,13 @@
+use crate::git::FileBackup;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum StageAction {
     Stage,
     Unstage,
+    Discard(Vec<FileBackup>),
+    // Full patch text (file headers + hunk) applied to the index
+    StageHunk(String),
+    UnstageHunk(String),
 }
 
 #[derive(Debug, Clone)]
@@ -74,4 +80,21 @@ mod tests {
         assert_eq!(history.undo_stack.len(), 1);
         assert_eq!(history.redo_stack.len(), 0);
     }
+
+    #[test]
+    fn test_discard_keeps_backups() {
+        let mut history = ActionHistory::default();
+        let backup = FileBackup {
+            path: "a.rs".to_string(),
+            before: Some("abc123".to_string()),
+            after: None,
+        };
+        history.push_action(
+            vec!["a.rs".to_string()],
+            StageAction::Discard(vec![backup.clone()]),
+        );
+
+        let entry = history.undo().unwrap();
+        assert_eq!(entry.action, StageAction::Discard(vec![backup]));
+    }
 }
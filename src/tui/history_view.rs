@@ -0,0 +1,32 @@
+use anyhow::Result;
+
+use super::app::SavedDiff;
+use super::list_cursor::ListCursor;
+use crate::git::{self, history::HistoryEntry};
+
+/// The commits that touched a file (followed across renames) or directory.
+pub struct HistoryView {
+    pub path: String,
+    pub entries: Vec<HistoryEntry>,
+    pub cursor: ListCursor,
+    pub previous_diff: Option<SavedDiff>, // Diff to restore when going back to it
+}
+
+impl HistoryView {
+    pub fn open(path: &str, is_dir: bool) -> Result<Self> {
+        Ok(HistoryView {
+            path: path.to_string(),
+            entries: git::history::file_history(path, is_dir)?,
+            cursor: ListCursor::default(),
+            previous_diff: None,
+        })
+    }
+
+    pub fn move_by(&mut self, amount: isize) {
+        self.cursor.move_by(amount, self.entries.len());
+    }
+
+    pub fn selected_entry(&self) -> Option<&HistoryEntry> {
+        self.entries.get(self.cursor.selected)
+    }
+}
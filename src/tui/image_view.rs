@@ -0,0 +1,177 @@
+use anyhow::{bail, Result};
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+
+use crate::git::{self, preview::format_size, DiffMode};
+
+const EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "webp", "ico", "tif", "tiff",
+];
+
+/// Whether `path` looks like an image the diff view can draw.
+pub fn is_image(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, ext)| EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// One version of an image, decoded once and redrawn only when the pane
+/// size changes.
+pub struct ImageSide {
+    pub label: &'static str,
+    pub bytes: u64,
+    pub image: Result<DynamicImage, String>,
+    cache: Option<((u16, u16), Vec<Line<'static>>)>,
+}
+
+impl ImageSide {
+    fn load(label: &'static str, bytes: Vec<u8>) -> Self {
+        ImageSide {
+            label,
+            bytes: bytes.len() as u64,
+            image: image::load_from_memory(&bytes).map_err(|e| e.to_string()),
+            cache: None,
+        }
+    }
+
+    /// Dimensions and file size, e.g. `640×480, 12.3 KiB`.
+    pub fn summary(&self) -> String {
+        match &self.image {
+            Ok(image) => {
+                let (w, h) = image.dimensions();
+                format!("{}×{}, {}", w, h, format_size(self.bytes))
+            }
+            Err(_) => format!("unreadable, {}", format_size(self.bytes)),
+        }
+    }
+
+    pub fn lines(&mut self, width: u16, height: u16) -> Vec<Line<'static>> {
+        let image = match &self.image {
+            Ok(image) => image,
+            Err(e) => return vec![Line::from(format!("Cannot decode image: {}", e))],
+        };
+        match &self.cache {
+            Some((size, lines)) if *size == (width, height) => lines.clone(),
+            _ => {
+                let lines = half_blocks(image, width, height);
+                self.cache = Some(((width, height), lines.clone()));
+                lines
+            }
+        }
+    }
+}
+
+/// The two sides of a changed image. A side is missing when the file was
+/// added or deleted.
+pub struct ImageDiff {
+    pub old: Option<ImageSide>,
+    pub new: Option<ImageSide>,
+}
+
+impl ImageDiff {
+    pub fn load(path: &str, mode: DiffMode, untracked: bool) -> Result<Self> {
+        let worktree = || git::read_worktree_bytes(path).ok();
+        let (old, new) = if untracked {
+            (None, worktree().map(|b| ImageSide::load("Worktree", b)))
+        } else {
+            let (old_label, old_rev) = match mode {
+                DiffMode::Worktree => ("Index", ""),
+                DiffMode::Index | DiffMode::Head => ("HEAD", "HEAD"),
+            };
+            let old = git::show_file_bytes(old_rev, path)
+                .ok()
+                .map(|b| ImageSide::load(old_label, b));
+            let new = if mode.shows_worktree() {
+                worktree().map(|b| ImageSide::load("Worktree", b))
+            } else {
+                git::show_file_bytes("", path)
+                    .ok()
+                    .map(|b| ImageSide::load("Index", b))
+            };
+            (old, new)
+        };
+        if old.is_none() && new.is_none() {
+            bail!("No version of {} to show", path);
+        }
+        Ok(ImageDiff { old, new })
+    }
+}
+
+/// Draws `image` scaled to fit `width` × `height` cells, two pixels per cell
+/// using the upper half block. Transparent areas show a checkerboard.
+pub fn half_blocks(image: &DynamicImage, width: u16, height: u16) -> Vec<Line<'static>> {
+    let (w, h) = image.dimensions();
+    if width == 0 || height == 0 || w == 0 || h == 0 {
+        return Vec::new();
+    }
+    let (max_w, max_h) = (width as u32, height as u32 * 2);
+    // Blurring small icons while enlarging them helps nobody
+    let filter = if w < max_w && h < max_h {
+        FilterType::Nearest
+    } else {
+        FilterType::Triangle
+    };
+    let scaled = image.resize(max_w, max_h, filter).to_rgba8();
+    let (w, h) = scaled.dimensions();
+    let pad = " ".repeat(((max_w - w) / 2) as usize);
+
+    let color = |x: u32, y: u32| {
+        let checker = if (x / 4 + y / 4).is_multiple_of(2) {
+            60
+        } else {
+            90
+        };
+        if y >= h {
+            return Color::Reset;
+        }
+        let [r, g, b, a] = scaled.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + checker * (255 - a as u32)) / 255) as u8;
+        Color::Rgb(blend(r), blend(g), blend(b))
+    };
+
+    (0..h)
+        .step_by(2)
+        .map(|y| {
+            let mut spans = vec![Span::raw(pad.clone())];
+            spans.extend(
+                (0..w).map(|x| {
+                    Span::styled("▀", Style::default().fg(color(x, y)).bg(color(x, y + 1)))
+                }),
+            );
+            Line::from(spans)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_is_image() {
+        assert!(is_image("assets/logo.PNG"));
+        assert!(is_image("a.b/photo.jpeg"));
+        assert!(!is_image("src/main.rs"));
+        assert!(!is_image("png"));
+    }
+
+    #[test]
+    fn test_half_blocks() {
+        // Red on top, opaque blue below, transparent bottom row
+        let mut img = RgbaImage::new(2, 4);
+        for x in 0..2 {
+            img.put_pixel(x, 0, Rgba([255, 0, 0, 255]));
+            img.put_pixel(x, 1, Rgba([0, 0, 255, 255]));
+        }
+        let lines = half_blocks(&DynamicImage::ImageRgba8(img), 2, 2);
+        assert_eq!(lines.len(), 2);
+        let cell = &lines[0].spans[1];
+        assert_eq!(cell.content, "▀");
+        assert_eq!(cell.style.fg, Some(Color::Rgb(255, 0, 0)));
+        assert_eq!(cell.style.bg, Some(Color::Rgb(0, 0, 255)));
+        assert_eq!(lines[1].spans[1].style.fg, Some(Color::Rgb(60, 60, 60)));
+    }
+}
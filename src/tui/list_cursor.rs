@@ -0,0 +1,48 @@
+/// Selection and scroll position of a list drawn one entry per row.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ListCursor {
+    pub selected: usize,
+    pub scroll: usize,
+}
+
+impl ListCursor {
+    /// Moves the selection by `amount`, staying within a list of `len` entries.
+    pub fn move_by(&mut self, amount: isize, len: usize) {
+        self.selected = self
+            .selected
+            .saturating_add_signed(amount)
+            .min(len.saturating_sub(1));
+    }
+
+    /// Keeps the selected entry within a window of `height` rows.
+    pub fn scroll_into_view(&mut self, height: usize) {
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if height > 0 && self.selected >= self.scroll + height {
+            self.scroll = self.selected + 1 - height;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_and_scroll() {
+        let mut cursor = ListCursor::default();
+        cursor.move_by(-1, 10);
+        assert_eq!(cursor.selected, 0);
+        cursor.move_by(isize::MAX, 10);
+        assert_eq!(cursor.selected, 9);
+
+        cursor.scroll_into_view(4);
+        assert_eq!(cursor.scroll, 6);
+        cursor.move_by(-5, 10);
+        cursor.scroll_into_view(4);
+        assert_eq!(cursor.scroll, 4);
+
+        cursor.move_by(1, 0);
+        assert_eq!(cursor.selected, 0);
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+
+use super::app::{AppLayout, SavedDiff};
+use super::list_cursor::ListCursor;
+use crate::git::log::{self, Graph, GraphCell, LogEntry, LOG_PAGE};
+
+/// Pages of history one search reads before giving up, so a query matching
+/// nothing doesn't read all of it.
+const SEARCH_PAGES: usize = 10;
+
+/// How a search of the log ended.
+#[derive(Debug, PartialEq)]
+pub enum SearchResult {
+    Found,
+    NotFound,
+    Stopped(usize), // Gave up after searching this many commits
+}
+
+/// The commit graph of every branch, read a page at a time as it is scrolled.
+pub struct LogView {
+    pub entries: Vec<LogEntry>,
+    pub rows: Vec<Vec<GraphCell>>, // Graph cells of each entry
+    graph: Graph,
+    complete: bool, // The last page has been read
+    pub cursor: ListCursor,
+    pub search_query: String,
+    pub is_typing_search: bool,
+    pub tree_layout: AppLayout, // Layout to restore when leaving a commit's tree
+    pub previous_diff: Option<SavedDiff>, // Diff to restore when going back to it
+}
+
+impl LogView {
+    pub fn open(tree_layout: AppLayout) -> Result<Self> {
+        let mut view = LogView {
+            entries: Vec::new(),
+            rows: Vec::new(),
+            graph: Graph::default(),
+            complete: false,
+            cursor: ListCursor::default(),
+            search_query: String::new(),
+            is_typing_search: false,
+            tree_layout,
+            previous_diff: None,
+        };
+        view.load_page()?;
+        Ok(view)
+    }
+
+    /// Reads the next page of history, if any is left.
+    fn load_page(&mut self) -> Result<bool> {
+        if self.complete {
+            return Ok(false);
+        }
+        let page = log::log_page(self.entries.len())?;
+        self.complete = page.len() < LOG_PAGE;
+        for entry in &page {
+            self.rows.push(self.graph.row(entry));
+        }
+        self.entries.extend(page);
+        Ok(true)
+    }
+
+    /// Moves the selection, reading more history when it nears the end.
+    pub fn move_by(&mut self, amount: isize) -> Result<()> {
+        let target = self.cursor.selected.saturating_add_signed(amount);
+        while target.saturating_add(LOG_PAGE / 4) >= self.entries.len() && self.load_page()? {
+            if amount == isize::MAX {
+                // Jumping to the bottom reads one more page, not all of history
+                break;
+            }
+        }
+        self.cursor.move_by(amount, self.entries.len());
+        Ok(())
+    }
+
+    pub fn selected_entry(&self) -> Option<&LogEntry> {
+        self.entries.get(self.cursor.selected)
+    }
+
+    /// Selects the next commit matching the search, reading up to
+    /// `SEARCH_PAGES` further pages for one to turn up.
+    pub fn search(&mut self, forward: bool) -> Result<SearchResult> {
+        if self.search_query.is_empty() {
+            return Ok(SearchResult::NotFound);
+        }
+        if forward {
+            let mut from = self.cursor.selected + 1;
+            let mut pages = 0;
+            loop {
+                let found = self.entries[from.min(self.entries.len())..]
+                    .iter()
+                    .position(|e| e.matches(&self.search_query));
+                if let Some(i) = found {
+                    self.cursor.selected = from + i;
+                    return Ok(SearchResult::Found);
+                }
+                from = self.entries.len();
+                if pages == SEARCH_PAGES {
+                    let searched = self.entries.len() - (self.cursor.selected + 1);
+                    return Ok(SearchResult::Stopped(searched));
+                }
+                if !self.load_page()? {
+                    return Ok(SearchResult::NotFound);
+                }
+                pages += 1;
+            }
+        }
+        let found = self.entries[..self.cursor.selected]
+            .iter()
+            .rposition(|e| e.matches(&self.search_query));
+        if let Some(i) = found {
+            self.cursor.selected = i;
+            return Ok(SearchResult::Found);
+        }
+        Ok(SearchResult::NotFound)
+    }
+}
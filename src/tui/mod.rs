@@ -7,23 +7,41 @@ use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
 
 mod app;
+mod blame_view;
+mod branch_modal;
+mod diff_model;
+mod diff_tool;
 mod event;
+mod file_view;
+mod highlight;
 mod history;
+mod history_view;
+mod image_view;
+mod list_cursor;
+mod log_view;
+mod side_by_side;
 mod ui;
+mod word_diff;
+mod wrap;
 
-use app::App;
+use app::{App, FilterMode};
 use event::run_app;
 
 pub use crate::theme::Theme;
 
-pub fn run(indent: usize, collapse: bool, theme: Theme) -> Result<()> {
+pub fn run(indent: usize, collapse: bool, theme: Theme, show_ignored: bool) -> Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(indent, collapse, theme)?;
+    let filter_mode = if show_ignored {
+        FilterMode::Ignored
+    } else {
+        FilterMode::All
+    };
+    let mut app = App::new(indent, collapse, theme, filter_mode)?;
     let res = run_app(&mut terminal, &mut app);
 
     disable_raw_mode()?;
@@ -0,0 +1,313 @@
+use std::ops::Range;
+
+use crate::git::patch::parse_hunk_header;
+
+// Folding keeps this many unchanged lines next to each change...
+const FOLD_KEEP: usize = 3;
+// ...and only hides runs of at least this many lines
+const FOLD_MIN: usize = 4;
+
+/// One side of a side-by-side row: the line of the raw diff it shows and its
+/// line number in the old or new file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub raw: usize,
+    pub line_no: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Row {
+    Full(usize), // File separators, headers and @@ lines span both columns
+    Split {
+        old: Option<Cell>,
+        new: Option<Cell>,
+    },
+    Folded(Range<usize>), // Unchanged lines hidden behind one marker
+}
+
+impl Row {
+    pub fn first_raw(&self) -> usize {
+        match self {
+            Row::Full(raw) => *raw,
+            Row::Split { old, new } => old.or(*new).map(|c| c.raw).unwrap_or(0),
+            Row::Folded(range) => range.start,
+        }
+    }
+
+    fn contains(&self, raw: usize) -> bool {
+        match self {
+            Row::Full(r) => *r == raw,
+            Row::Split { old, new } => [old, new].iter().any(|c| c.is_some_and(|c| c.raw == raw)),
+            Row::Folded(range) => range.contains(&raw),
+        }
+    }
+}
+
+/// Pairs the lines of a unified diff into aligned old/new rows. Context lines
+/// show on both sides; a run of removals is matched line by line with the
+/// additions that follow it, and the shorter side is padded with gaps.
+pub fn pair_lines(diff_content: &str) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut removed: Vec<Cell> = Vec::new();
+    let mut added: Vec<Cell> = Vec::new();
+    let (mut old_no, mut new_no) = (0, 0);
+    let mut in_hunk = false;
+
+    let flush = |rows: &mut Vec<Row>, removed: &mut Vec<Cell>, added: &mut Vec<Cell>| {
+        for i in 0..removed.len().max(added.len()) {
+            rows.push(Row::Split {
+                old: removed.get(i).copied(),
+                new: added.get(i).copied(),
+            });
+        }
+        removed.clear();
+        added.clear();
+    };
+
+    for (raw, line) in diff_content.lines().enumerate() {
+        if let Some((old_start, _, new_start, _, _)) = parse_hunk_header(line) {
+            flush(&mut rows, &mut removed, &mut added);
+            old_no = old_start;
+            new_no = new_start;
+            in_hunk = true;
+            rows.push(Row::Full(raw));
+            continue;
+        }
+        if line.starts_with("diff ") {
+            in_hunk = false;
+        }
+        if !in_hunk {
+            flush(&mut rows, &mut removed, &mut added);
+            rows.push(Row::Full(raw));
+            continue;
+        }
+
+        match line.chars().next() {
+            Some('-') => {
+                if !added.is_empty() {
+                    flush(&mut rows, &mut removed, &mut added);
+                }
+                removed.push(Cell {
+                    raw,
+                    line_no: old_no,
+                });
+                old_no += 1;
+            }
+            Some('+') => {
+                added.push(Cell {
+                    raw,
+                    line_no: new_no,
+                });
+                new_no += 1;
+            }
+            Some('\\') => {
+                flush(&mut rows, &mut removed, &mut added);
+                rows.push(Row::Full(raw));
+            }
+            _ => {
+                flush(&mut rows, &mut removed, &mut added);
+                rows.push(Row::Split {
+                    old: Some(Cell {
+                        raw,
+                        line_no: old_no,
+                    }),
+                    new: Some(Cell {
+                        raw,
+                        line_no: new_no,
+                    }),
+                });
+                old_no += 1;
+                new_no += 1;
+            }
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+/// Old and new line number of every raw diff line (`None` for headers and for
+/// the side a line is not on), for the unified view's gutter.
+pub fn line_numbers(diff_content: &str) -> Vec<(Option<usize>, Option<usize>)> {
+    let mut numbers = vec![(None, None); diff_content.lines().count()];
+    for row in pair_lines(diff_content) {
+        if let Row::Split { old, new } = row {
+            if let Some(cell) = old {
+                numbers[cell.raw].0 = Some(cell.line_no);
+            }
+            if let Some(cell) = new {
+                numbers[cell.raw].1 = Some(cell.line_no);
+            }
+        }
+    }
+    numbers
+}
+
+/// Raw line ranges of unchanged lines that can be folded away: the middle of
+/// every run of context lines, keeping `FOLD_KEEP` lines next to each change.
+pub fn unchanged_runs(diff_content: &str) -> Vec<Range<usize>> {
+    let mut folds = Vec::new();
+    let mut in_hunk = false;
+    let mut run: Option<(usize, bool)> = None; // Start, and whether it opens the hunk
+
+    let mut close = |run: &mut Option<(usize, bool)>, end: usize, closes_hunk: bool| {
+        if let Some((start, opens_hunk)) = run.take() {
+            let from = if opens_hunk { start } else { start + FOLD_KEEP };
+            let to = if closes_hunk {
+                end
+            } else {
+                end.saturating_sub(FOLD_KEEP)
+            };
+            if to >= from + FOLD_MIN {
+                folds.push(from..to);
+            }
+        }
+    };
+
+    let mut last = 0;
+    for (raw, line) in diff_content.lines().enumerate() {
+        last = raw + 1;
+        if line.starts_with("@@") || line.starts_with("diff ") {
+            close(&mut run, raw, true);
+            in_hunk = line.starts_with("@@");
+            if in_hunk {
+                run = Some((raw + 1, true));
+            }
+            continue;
+        }
+        if !in_hunk {
+            continue;
+        }
+        if line.is_empty() || line.starts_with(' ') {
+            if run.is_none() {
+                run = Some((raw, false));
+            }
+        } else {
+            close(&mut run, raw, false);
+        }
+    }
+    close(&mut run, last, true);
+    folds
+}
+
+/// Replaces the rows inside `folds` with one `Row::Folded` per fold.
+pub fn fold_rows(rows: Vec<Row>, folds: &[Range<usize>]) -> Vec<Row> {
+    let mut out: Vec<Row> = Vec::with_capacity(rows.len());
+    let mut folds = folds.iter().peekable();
+    for row in rows {
+        let raw = row.first_raw();
+        while folds.next_if(|f| f.end <= raw).is_some() {}
+        match folds.peek() {
+            Some(fold) if fold.contains(&raw) => {
+                let folded = Row::Folded((*fold).clone());
+                if out.last() != Some(&folded) {
+                    out.push(folded);
+                }
+            }
+            _ => out.push(row),
+        }
+    }
+    out
+}
+
+/// Index of the row that shows raw diff line `raw` (or the first one after it).
+pub fn row_at(rows: &[Row], raw: usize) -> usize {
+    rows.iter()
+        .position(|r| r.contains(raw))
+        .or_else(|| rows.iter().position(|r| r.first_raw() > raw))
+        .unwrap_or(rows.len().saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(raw: usize, line_no: usize) -> Option<Cell> {
+        Some(Cell { raw, line_no })
+    }
+
+    #[test]
+    fn test_pair_lines() {
+        let diff = "diff --git a/f b/f\n@@ -3,4 +3,3 @@\n a\n-b\n-c\n+B\n d\n";
+        let rows = pair_lines(diff);
+        assert_eq!(
+            rows,
+            vec![
+                Row::Full(0),
+                Row::Full(1),
+                Row::Split {
+                    old: cell(2, 3),
+                    new: cell(2, 3)
+                },
+                Row::Split {
+                    old: cell(3, 4),
+                    new: cell(5, 4)
+                },
+                Row::Split {
+                    old: cell(4, 5),
+                    new: None
+                },
+                Row::Split {
+                    old: cell(6, 6),
+                    new: cell(6, 5)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pair_lines_addition_before_removal() {
+        let rows = pair_lines("@@ -1,1 +1,1 @@\n+x\n-y\n");
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1],
+            Row::Split {
+                old: None,
+                new: cell(1, 1)
+            }
+        );
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let numbers = line_numbers("@@ -3,2 +3,2 @@\n a\n-b\n+B\n");
+        assert_eq!(
+            numbers,
+            vec![
+                (None, None),
+                (Some(3), Some(3)),
+                (Some(4), None),
+                (None, Some(4))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unchanged_runs() {
+        let context: String = (0..10).map(|i| format!(" c{}\n", i)).collect();
+        // Leading context (raw 1-10), a change, inner context (12-21), a change,
+        // trailing context (23-32)
+        let diff = format!("@@ -1,32 +1,32 @@\n{0}-x\n{0}+y\n{0}", context);
+        let folds = unchanged_runs(&diff);
+        assert_eq!(folds, vec![1..8, 15..19, 26..33]);
+
+        let rows = fold_rows(pair_lines(&diff), &folds);
+        assert_eq!(rows[1], Row::Folded(1..8));
+        assert_eq!(rows[2].first_raw(), 8);
+        assert_eq!(row_at(&rows, 16), 9);
+    }
+
+    #[test]
+    fn test_unchanged_runs_short() {
+        assert!(unchanged_runs("@@ -1,7 +1,7 @@\n a\n b\n c\n-x\n+y\n d\n e\n f\n").is_empty());
+    }
+
+    #[test]
+    fn test_row_at() {
+        let rows = pair_lines("@@ -1,2 +1,2 @@\n-a\n-b\n+A\n+B\n");
+        // "+A" (raw 3) is shown next to "-a" in row 1
+        assert_eq!(row_at(&rows, 3), 1);
+        assert_eq!(row_at(&rows, 2), 2);
+        assert_eq!(row_at(&rows, 4), 2);
+        assert_eq!(row_at(&rows, 99), 2);
+    }
+}
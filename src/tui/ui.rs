@@ -5,14 +5,47 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem, Paragraph},
     Frame,
 };
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use unicode_width::UnicodeWidthStr;
 
-use super::app::{App, AppLayout, Focus, ViewMode};
+use super::app::{App, AppLayout, DiffLayout, DiffTarget, DiscardRequest, Focus, ViewMode};
+use super::branch_modal::{BranchConfirm, BranchInput};
+use super::file_view::{FileSide, Marker};
+use super::image_view::{ImageDiff, ImageSide};
+use super::list_cursor::ListCursor;
+use super::side_by_side;
+use super::word_diff;
+use super::wrap;
+use crate::git::log::GraphCell;
 use crate::node::FlatNode;
 use crate::theme::Theme;
 
 pub fn ui(f: &mut Frame, app: &mut App) {
+    if app.view_mode == ViewMode::File {
+        render_file_view(f, app);
+        return;
+    }
+    if app.view_mode == ViewMode::Blame {
+        render_blame_view(f, app);
+        return;
+    }
+    if app.view_mode == ViewMode::History {
+        render_history_view(f, app);
+        return;
+    }
+    if app.view_mode == ViewMode::Log {
+        render_log_view(f, app);
+        return;
+    }
+
     if app.view_mode == ViewMode::Diff {
+        app.sync_diff_model();
+        if app.syntax_highlight && !app.diff_deferred {
+            app.highlight.update(&app.diff_content);
+        }
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -24,98 +57,144 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 }),
             ])
             .split(f.size());
-
-        let text = if app.patch_mode {
-            let mut lines = Vec::new();
-            let raw_lines: Vec<&str> = app.diff_content.lines().collect();
-            let selected_hunk_range = app.selected_hunk_idx.and_then(|idx| {
-                app.diff_hunks
-                    .get(idx)
-                    .map(|hunk| (hunk.display_start, hunk.display_end))
-            });
-
-            for (i, line) in raw_lines.iter().enumerate() {
-                let mut style = Style::default();
-
-                // Basic syntax highlighting
-                if line.starts_with('+') {
-                    style = style.fg(Color::Green);
-                } else if line.starts_with('-') {
-                    style = style.fg(Color::Red);
-                } else if line.starts_with("@@") {
-                    style = style.fg(Color::Cyan);
-                }
-
-                // Selection Highlighting
-                if let Some((start, end)) = selected_hunk_range {
-                    if i >= start && i <= end {
-                        style = style
-                            .bg(Color::Rgb(50, 50, 50))
-                            .add_modifier(Modifier::BOLD);
-                    } else {
-                        style = style.add_modifier(Modifier::DIM); // Dim others
-                    }
-                }
-
-                lines.push(Line::from(Span::styled(*line, style)));
-            }
-            ratatui::text::Text::from(lines)
+        let inner_width = chunks[0].width.saturating_sub(2) as usize;
+        let inner_height = chunks[0].height.saturating_sub(2) as usize;
+
+        let hints = if !app.patch_mode {
+            "p to Patch, m to Switch Sides"
+        } else if app.line_mode {
+            "Line Select: Space to Toggle, Enter to Apply, Esc to Exit"
+        } else if app.diff_mode == crate::git::DiffMode::Index {
+            "Patch Mode: Space to Unstage, S to Split, e to Edit, p to Exit"
         } else {
-            let mut lines = Vec::new();
-            for line in app.diff_content.lines() {
-                let mut style = Style::default();
-                if line.starts_with('+') {
-                    style = style.fg(Color::Green);
-                } else if line.starts_with('-') {
-                    style = style.fg(Color::Red);
-                } else if line.starts_with("@@") {
-                    style = style.fg(Color::Cyan);
-                }
-                lines.push(Line::from(Span::styled(line, style)));
+            "Patch Mode: Space to Stage, x to Discard, S to Split, e to Edit, p to Exit"
+        };
+        let is_review = app.diff_target == Some(DiffTarget::Review);
+        let progress = match app.hunk_progress() {
+            Some((file, files, hunk, hunks)) if is_review || files > 1 => {
+                format!("file {}/{}, hunk {}/{} | ", file, files, hunk, hunks)
             }
-            ratatui::text::Text::from(lines)
+            _ => String::new(),
         };
-
-        let title = if app.patch_mode {
-            " Diff (Patch Mode: Space to Stage, p to Exit) "
+        let options = app.diff_options.summary();
+        let title = if matches!(app.diff_target, Some(DiffTarget::Preview(_))) {
+            " Preview [Untracked] (Space in the tree to Stage, i to Ignore) ".to_string()
+        } else if let Some(DiffTarget::Commit(commit, paths)) = &app.diff_target {
+            format!(
+                " Commit {}{} (q to Go Back) ",
+                &commit[..commit.len().min(7)],
+                if paths.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", paths.join(", "))
+                }
+            )
         } else {
-            " Diff (p to Patch) "
+            format!(
+                " {} [{}{}{}] ({}{}) ",
+                if is_review { "Review" } else { "Diff" },
+                app.diff_mode.as_str(),
+                if options.is_empty() { "" } else { " | " },
+                options,
+                progress,
+                hints
+            )
         };
 
-        let paragraph = Paragraph::new(text)
-            .block(Block::default().borders(Borders::ALL).title(title))
-            .scroll((app.diff_scroll, 0));
-        f.render_widget(paragraph, chunks[0]);
+        let mut block = Block::default().borders(Borders::ALL).title(title);
+        if let Some(msg) = &app.status_message {
+            block = block.title_bottom(Span::styled(
+                format!(" {} ", msg),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
 
-        if app.is_diff_search || !app.diff_search_query.is_empty() {
-            let count_text = if app.diff_matches.is_empty() {
-                " (no matches) ".to_string()
-            } else {
-                format!(
-                    " [{}/{}] ",
-                    app.current_diff_match.map(|i| i + 1).unwrap_or(0),
-                    app.diff_matches.len()
-                )
-            };
+        // twig.diff.tool output, unless it failed; patch mode always shows the raw patch
+        let mut use_tool = false;
+        let plain = app.patch_mode || app.diff_deferred || app.image_diff.is_some();
+        if let (Some(tool), false) = (&app.diff_tool, plain) {
+            app.diff_tool_cache
+                .update(tool, &app.diff_content, inner_width as u16);
+            match &app.diff_tool_cache.output {
+                Some(Ok(_)) => use_tool = true,
+                Some(Err(e)) => {
+                    block = block.title_bottom(Span::styled(
+                        format!(" {} ", e),
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                None => {}
+            }
+        }
 
-            let search_bar = Paragraph::new(Line::from(vec![
-                Span::styled(" Search: ", Style::default().fg(Color::Yellow)),
-                Span::raw(&app.diff_search_query),
-                Span::styled(count_text, Style::default().fg(Color::DarkGray)),
-            ]))
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Yellow)),
+        // The width is kept for match jumping, which scrolls sideways to bring
+        // a match into view; neither it nor scrolling goes past the longest
+        // line on screen
+        let tool_lines = match &app.diff_tool_cache.output {
+            Some(Ok(lines)) if use_tool => Some(lines),
+            _ => None,
+        };
+        let (text_width, longest) = match tool_lines {
+            Some(lines) => (
+                inner_width,
+                lines
+                    .iter()
+                    .skip(app.diff_scroll)
+                    .take(inner_height)
+                    .map(Line::width)
+                    .max()
+                    .unwrap_or(0),
+            ),
+            None => (
+                match app.diff_layout {
+                    DiffLayout::Unified => {
+                        inner_width.saturating_sub(app.diff_model.number_width * 2 + 3)
+                    }
+                    DiffLayout::SideBySide => (inner_width / 2).saturating_sub(7),
+                },
+                longest_diff_line(app, inner_height),
+            ),
+        };
+        app.diff_text_width = text_width;
+        app.diff_height = inner_height;
+        app.diff_hscroll = app.diff_hscroll.min(longest.saturating_sub(text_width));
+
+        if let Some(images) = app.image_diff.as_mut() {
+            render_image_diff(f, images, block, chunks[0]);
+        } else if app.diff_deferred {
+            let text = format!(
+                "Diff is too large to show right away ({} lines). Press L to load it.",
+                app.diff_model.len()
             );
-            f.render_widget(search_bar, chunks[1]);
+            let paragraph =
+                Paragraph::new(Span::styled(text, Style::default().fg(Color::Yellow))).block(block);
+            f.render_widget(paragraph, chunks[0]);
+        } else if let Some(lines) = tool_lines {
+            let visible: Vec<Line> = lines
+                .iter()
+                .skip(app.diff_scroll)
+                .flat_map(|line| {
+                    let spans = line.spans.clone();
+                    if app.diff_wrap {
+                        wrap::wrap_spans(spans, inner_width)
+                    } else {
+                        vec![wrap::skip_columns(spans, app.diff_hscroll)]
+                    }
+                })
+                .take(inner_height)
+                .map(Line::from)
+                .collect();
+            f.render_widget(Paragraph::new(visible).block(block), chunks[0]);
+        } else if app.diff_layout == DiffLayout::SideBySide {
+            render_side_by_side(f, app, block, chunks[0]);
+        } else {
+            render_unified(f, app, block, chunks[0]);
+        }
 
-            if app.is_diff_search {
-                f.set_cursor(
-                    chunks[1].x + 10 + app.diff_search_query.width() as u16,
-                    chunks[1].y + 1,
-                );
-            }
+        render_diff_search(f, app, chunks[1]);
+
+        if app.pending_discard.is_some() {
+            render_discard_dialog(f, app);
         }
         return;
     }
@@ -127,7 +206,13 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 .constraints([Constraint::Min(0), Constraint::Length(3)])
                 .split(f.size());
 
-            let title = if app.layout == AppLayout::Compact {
+            let title = if let Some(entry) = &app.viewed_commit {
+                format!(
+                    " Commit {}: {} (q to Go Back to the Log) ",
+                    entry.short_commit(),
+                    entry.subject
+                )
+            } else if app.layout == AppLayout::Compact {
                 format!(
                     " git-twig interactive | Filter: {} (Compact) ",
                     app.filter_mode.as_str()
@@ -154,6 +239,7 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 Focus::Unstaged,
                 visual_range,
                 app.layout == AppLayout::EasterEgg,
+                &app.ignore_rules,
             );
 
             render_bottom_bar(f, app, chunks[1]);
@@ -186,6 +272,7 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 app.focus,
                 staged_visual_range,
                 false,
+                &app.ignore_rules,
             );
 
             let filtered_unstaged = App::filter_nodes(&app.unstaged_nodes, &app.search_query);
@@ -206,6 +293,7 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 app.focus,
                 unstaged_visual_range,
                 false,
+                &app.ignore_rules,
             );
 
             render_bottom_bar(f, app, chunks[2]);
@@ -231,6 +319,7 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 Focus::Unstaged,
                 visual_range,
                 true,
+                &app.ignore_rules,
             );
 
             render_bottom_bar(f, app, chunks[1]);
@@ -245,9 +334,21 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         render_worktree_selector(f, app);
     }
 
+    if app.branch_modal.is_some() {
+        render_branch_modal(f, app);
+    }
+
     if app.show_commit_dialog {
         render_commit_dialog(f, app);
     }
+
+    if app.show_ignore_dialog {
+        render_ignore_dialog(f, app);
+    }
+
+    if app.pending_discard.is_some() {
+        render_discard_dialog(f, app);
+    }
 }
 
 fn render_help_modal(f: &mut Frame, app: &mut App) {
@@ -277,9 +378,32 @@ fn render_help_modal(f: &mut Frame, app: &mut App) {
             Style::default().add_modifier(Modifier::UNDERLINED),
         )]),
         Line::from("  Space : Stage/Unstage file or directory"),
-        Line::from("  Enter : View inline diff"),
+        Line::from("  x     : Discard working-tree changes (file, dir, selection or hunk)"),
+        Line::from("  p     : Patch mode in diff (Space: stage hunk, V: pick lines)"),
+        Line::from("  S/e   : Split hunk / edit hunk in git's editor (patch mode)"),
+        Line::from("  R     : Review all changes hunk by hunk ([/]: next/prev file)"),
+        Line::from("  Enter : Combined diff on a directory or visual selection"),
+        Line::from("  Tab   : Fold/unfold file at top of a diff"),
+        Line::from("  m     : Cycle diff sides (index/worktree, HEAD/index, HEAD/worktree)"),
+        Line::from("  v     : Unified / side-by-side diff (remembered)"),
+        Line::from("  H     : Toggle syntax highlighting in diffs (remembered)"),
+        Line::from("  w/a/M : Diff whitespace (-w/-b) / algorithm / rename threshold"),
+        Line::from("  +/-   : More/less diff context"),
+        Line::from("  K/J   : Show more context above/below a hunk"),
+        Line::from("  F     : Fold long runs of unchanged lines"),
+        Line::from("  L     : Load a diff held back for its size"),
+        Line::from("  h/l   : Scroll diff sideways (zh/zl: one column)"),
+        Line::from("  W     : Wrap long diff lines"),
+        Line::from("  b     : Blame the file (Enter on a line: show its commit)"),
+        Line::from("  T     : History of the file or directory (Enter: show the commit)"),
+        Line::from("  C     : Commit log graph (/ to search, Enter: browse the commit)"),
+        Line::from(
+            "  B     : Branches (Enter: checkout, n/N: new, r: rename, d: delete, u: upstream)",
+        ),
+        Line::from("  o     : View the whole file with changes marked (]/[: next/prev change)"),
+        Line::from("  Enter : View inline diff (or a preview: images, untracked dirs, big files)"),
         Line::from("  /     : Search files"),
-        Line::from("  f     : Toggle Filter (Unified view)"),
+        Line::from("  f     : Toggle Filter (All/Modified/Staged/Ignored)"),
         Line::from("  t      : Cycle Theme (Ascii/Unicode/Rounded/Nerd)"),
         Line::from("  V      : Visual Selection Mode"),
         Line::from("  Tab    : Switch Pane (Split view)"),
@@ -297,9 +421,10 @@ fn render_help_modal(f: &mut Frame, app: &mut App) {
         )]),
         Line::from("  gg    : Jump to top"),
         Line::from("  G     : Jump to bottom"),
-        Line::from("  u/Ctrl+r : Undo / Redo stage"),
+        Line::from("  u/Ctrl+r : Undo / Redo stage or discard"),
         Line::from("  Alt+V : Easter Egg tree view"),
         Line::from("  y     : Yank path to clipboard"),
+        Line::from("  i     : Add untracked path(s) to .gitignore / exclude"),
         Line::from("  w     : Switch Worktree"),
         Line::from(""),
         Line::from(vec![Span::styled(
@@ -339,6 +464,807 @@ fn render_help_modal(f: &mut Frame, app: &mut App) {
     f.render_widget(paragraph, area);
 }
 
+/// A whole file with added, modified and removed lines marked in the gutter
+/// and the selected change highlighted.
+fn render_file_view(f: &mut Frame, app: &mut App) {
+    let Some(view) = &app.file_view else {
+        return;
+    };
+    let searching = app.is_diff_search || !app.diff_search_query.is_empty();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(if searching { 3 } else { 0 }),
+        ])
+        .split(f.size());
+
+    let hint = if view.side.can_stage() {
+        let verb = if view.side == FileSide::Index {
+            "Unstage"
+        } else {
+            "Stage"
+        };
+        format!("]/[ to Jump, Space to {}, m to Switch Side", verb)
+    } else {
+        "]/[ to Jump, m to Switch Side".to_string()
+    };
+    let progress = match view.selected {
+        Some(i) => format!("change {}/{} | ", i + 1, view.hunks.len()),
+        None => format!("{} changes | ", view.hunks.len()),
+    };
+    let title = format!(
+        " File: {} [{}] ({}{}) ",
+        view.path,
+        view.side.as_str(),
+        progress,
+        hint
+    );
+    let mut block = Block::default().borders(Borders::ALL).title(title);
+    if let Some(msg) = &app.status_message {
+        block = block.title_bottom(Span::styled(
+            format!(" {} ", msg),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+    let inner = block.inner(chunks[0]);
+    f.render_widget(block, chunks[0]);
+
+    let width = view.lines.len().to_string().len();
+    let height = inner.height as usize;
+    let gutter_style = Style::default().fg(Color::DarkGray);
+    let continuation = Span::styled(format!("{:>w$}   ", "↪", w = width), gutter_style);
+    let text_width = (inner.width as usize).saturating_sub(width + 3);
+
+    let mut lines = Vec::new();
+    for (i, text) in view.lines.iter().enumerate().skip(view.scroll) {
+        if lines.len() >= height {
+            break;
+        }
+        let marker = view.markers.get(i).copied().flatten();
+        let (symbol, color) = match marker.map(|(kind, _)| kind) {
+            Some(Marker::Added) => ("▎", Color::Green),
+            Some(Marker::Modified) => ("▎", Color::Yellow),
+            Some(Marker::Removed) => ("▁", Color::Red),
+            None => (" ", Color::Reset),
+        };
+        let gutter = Span::styled(format!("{:>w$} ", i + 1, w = width), gutter_style);
+        let mut style = Style::default();
+        if marker.is_some_and(|(_, hunk)| Some(hunk) == view.selected) {
+            style = style
+                .bg(Color::Rgb(50, 50, 50))
+                .add_modifier(Modifier::BOLD);
+        }
+        if app.current_diff_match.and_then(|m| app.diff_matches.get(m)) == Some(&i) {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        let content = vec![Span::styled(text.as_str(), style)];
+        let mut placed = place_content(app, gutter, &continuation, content, text_width);
+        // The marker stays put when the text scrolls sideways
+        placed[0].spans.insert(
+            1,
+            Span::styled(format!("{} ", symbol), Style::default().fg(color)),
+        );
+        lines.extend(placed);
+    }
+    lines.truncate(height);
+    f.render_widget(Paragraph::new(lines), inner);
+
+    render_diff_search(f, app, chunks[1]);
+    app.diff_text_width = text_width;
+}
+
+/// `git blame` with the commit, author and date of each line, brighter the
+/// more recent the commit. Runs of lines from one commit show it once.
+/// Width of the author column in the blame, history and log views.
+const AUTHOR_WIDTH: usize = 14;
+
+/// Draws the border of a full-screen list view, with the status message (or
+/// `hint` when there is none) along the bottom, and scrolls `cursor` so the
+/// selected row fits. Returns the area left for the rows.
+fn render_list_frame(
+    f: &mut Frame,
+    title: String,
+    status: Option<&str>,
+    hint: Option<Span<'static>>,
+    cursor: &mut ListCursor,
+) -> Rect {
+    let area = f.size();
+    let mut block = Block::default().borders(Borders::ALL).title(title);
+    if let Some(msg) = status {
+        block = block.title_bottom(Span::styled(
+            format!(" {} ", msg),
+            Style::default().fg(Color::Yellow),
+        ));
+    } else if let Some(hint) = hint {
+        block = block.title_bottom(hint);
+    }
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    cursor.scroll_into_view(inner.height as usize);
+    inner
+}
+
+/// Author name cut or padded to the author column.
+fn author_column(author: &str) -> String {
+    let author: String = author.chars().take(AUTHOR_WIDTH).collect();
+    format!("{:<w$}", author, w = AUTHOR_WIDTH)
+}
+
+/// Marks the row under the cursor in a list view.
+fn highlight_row(spans: &mut [Span]) {
+    for span in spans {
+        span.style = span
+            .style
+            .bg(Color::Rgb(50, 50, 50))
+            .add_modifier(Modifier::BOLD);
+    }
+}
+
+fn render_blame_view(f: &mut Frame, app: &mut App) {
+    let Some(view) = app.blame_view.as_mut() else {
+        return;
+    };
+    let title = format!(
+        " Blame: {} (Enter to Show Commit, q to Go Back) ",
+        view.path
+    );
+    let hint = view.selected_line().map(|line| {
+        let text = if line.is_uncommitted() {
+            " Not committed yet ".to_string()
+        } else {
+            format!(
+                " {} {}, {}: {} ",
+                line.short_commit(),
+                line.author,
+                line.date(),
+                line.summary
+            )
+        };
+        Span::styled(text, Style::default().fg(Color::DarkGray))
+    });
+    let inner = render_list_frame(
+        f,
+        title,
+        app.status_message.as_deref(),
+        hint,
+        &mut view.cursor,
+    );
+    let Some(view) = app.blame_view.as_ref() else {
+        return;
+    };
+
+    let height = inner.height as usize;
+    let number_width = view.lines.len().to_string().len();
+    let gutter_width = 7 + 1 + AUTHOR_WIDTH + 1 + 10 + 1 + number_width + 3;
+    let text_width = (inner.width as usize).saturating_sub(gutter_width);
+    let continuation = Span::raw(" ".repeat(gutter_width));
+
+    let mut lines = Vec::new();
+    for (i, line) in view.lines.iter().enumerate().skip(view.cursor.scroll) {
+        if lines.len() >= height {
+            break;
+        }
+        let color = if line.is_uncommitted() {
+            Color::Green
+        } else {
+            // From grey for the oldest commit to amber for the newest
+            let age = view.age(line);
+            let mix = |old: f64, new: f64| (old + (new - old) * age) as u8;
+            Color::Rgb(mix(100.0, 255.0), mix(100.0, 190.0), mix(110.0, 60.0))
+        };
+        let same_as_above = i > view.cursor.scroll && view.lines[i - 1].commit == line.commit;
+        let info = if same_as_above {
+            " ".repeat(7 + 1 + AUTHOR_WIDTH + 1 + 10)
+        } else if line.is_uncommitted() {
+            format!(
+                "{:<7} {} {:<10}",
+                "·······",
+                author_column("Not Committed"),
+                ""
+            )
+        } else {
+            format!(
+                "{} {} {}",
+                line.short_commit(),
+                author_column(&line.author),
+                line.date()
+            )
+        };
+        let gutter = Span::styled(
+            format!("{} {:>w$} │ ", info, i + 1, w = number_width),
+            Style::default().fg(color),
+        );
+        let mut content = vec![Span::raw(line.text.as_str())];
+        if i == view.cursor.selected {
+            highlight_row(&mut content);
+        }
+        lines.extend(place_content(
+            app,
+            gutter,
+            &continuation,
+            content,
+            text_width,
+        ));
+    }
+    lines.truncate(height);
+    f.render_widget(Paragraph::new(lines), inner);
+    app.diff_text_width = text_width;
+}
+
+/// Hash, date and author columns that start each row of the history and log
+/// views.
+fn commit_spans<'a>(short_commit: &'a str, date: &'a str, author: &str) -> Vec<Span<'a>> {
+    vec![
+        Span::styled(
+            format!("{} ", short_commit),
+            Style::default().fg(Color::Yellow),
+        ),
+        Span::styled(format!("{} ", date), Style::default().fg(Color::DarkGray)),
+        Span::styled(
+            format!("{} ", author_column(author)),
+            Style::default().fg(Color::Cyan),
+        ),
+    ]
+}
+
+fn render_history_view(f: &mut Frame, app: &mut App) {
+    let Some(view) = app.history_view.as_mut() else {
+        return;
+    };
+    let title = format!(
+        " History: {} (Enter to Show Commit, q to Go Back) ",
+        view.path
+    );
+    let hint = (view.entries.len() >= crate::git::history::HISTORY_LIMIT).then(|| {
+        Span::styled(
+            format!(" Showing the latest {} commits ", view.entries.len()),
+            Style::default().fg(Color::DarkGray),
+        )
+    });
+    let inner = render_list_frame(
+        f,
+        title,
+        app.status_message.as_deref(),
+        hint,
+        &mut view.cursor,
+    );
+
+    let lines: Vec<Line> = view
+        .entries
+        .iter()
+        .enumerate()
+        .skip(view.cursor.scroll)
+        .take(inner.height as usize)
+        .map(|(i, entry)| {
+            let mut spans = commit_spans(entry.short_commit(), &entry.date, &entry.author);
+            spans.extend([
+                Span::styled(
+                    format!("{:>6} ", format!("+{}", entry.added)),
+                    Style::default().fg(Color::Green),
+                ),
+                Span::styled(
+                    format!("{:>6} ", format!("-{}", entry.deleted)),
+                    Style::default().fg(Color::Red),
+                ),
+                Span::raw(entry.subject.as_str()),
+            ]);
+            if i == view.cursor.selected {
+                highlight_row(&mut spans);
+            }
+            Line::from(spans)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+fn render_log_view(f: &mut Frame, app: &mut App) {
+    let Some(view) = app.log_view.as_mut() else {
+        return;
+    };
+    let title = " Log (Enter to Browse Commit, / to Search, q to Go Back) ".to_string();
+    // The search prompt takes the place of any status message while typing
+    let (status, hint) = if view.is_typing_search {
+        (None, Some(Span::raw(format!(" /{} ", view.search_query))))
+    } else {
+        let hint = (!view.search_query.is_empty()).then(|| {
+            Span::styled(
+                format!(" Search: {} (n/N for next/previous) ", view.search_query),
+                Style::default().fg(Color::DarkGray),
+            )
+        });
+        (app.status_message.as_deref(), hint)
+    };
+    let inner = render_list_frame(f, title, status, hint, &mut view.cursor);
+
+    const LANE_COLORS: [Color; 6] = [
+        Color::Cyan,
+        Color::Green,
+        Color::Yellow,
+        Color::Magenta,
+        Color::Blue,
+        Color::Red,
+    ];
+    let theme = &app.theme;
+    let lines: Vec<Line> = view
+        .entries
+        .iter()
+        .zip(&view.rows)
+        .enumerate()
+        .skip(view.cursor.scroll)
+        .take(inner.height as usize)
+        .map(|(i, (entry, row))| {
+            let mut spans = commit_spans(entry.short_commit(), &entry.date, &entry.author);
+
+            // Links are drawn in the color of the commit's lane
+            let node_lane = row.iter().position(|c| *c == GraphCell::Node).unwrap_or(0) / 2;
+            for (col, cell) in row.iter().enumerate() {
+                let symbol = match cell {
+                    GraphCell::Empty => ' ',
+                    GraphCell::Lane => theme.tree_vertical,
+                    GraphCell::Node => theme.graph_node,
+                    GraphCell::Dash => theme.tree_dash,
+                    GraphCell::Branch => theme.tree_branch,
+                    GraphCell::Fork => theme.graph_fork,
+                    GraphCell::Join => theme.graph_join,
+                };
+                let lane = if *cell == GraphCell::Dash {
+                    node_lane
+                } else {
+                    col / 2
+                };
+                spans.push(Span::styled(
+                    symbol.to_string(),
+                    Style::default().fg(LANE_COLORS[lane % LANE_COLORS.len()]),
+                ));
+            }
+            spans.push(Span::raw(" "));
+
+            if !entry.refs.is_empty() {
+                spans.push(Span::styled("(", Style::default().fg(Color::Yellow)));
+                for (n, name) in entry.refs.iter().enumerate() {
+                    if n > 0 {
+                        spans.push(Span::styled(", ", Style::default().fg(Color::Yellow)));
+                    }
+                    spans.extend(ref_spans(name));
+                }
+                spans.push(Span::styled(") ", Style::default().fg(Color::Yellow)));
+            }
+            spans.push(Span::raw(entry.subject.as_str()));
+
+            if i == view.cursor.selected {
+                highlight_row(&mut spans);
+            }
+            Line::from(spans)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// A ref decoration, shortened and colored the way `git log` does: HEAD cyan,
+/// local branches green, remote branches red, tags yellow.
+fn ref_spans(name: &str) -> Vec<Span<'static>> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let mut spans = Vec::new();
+    let mut name = name;
+    if let Some(branch) = name.strip_prefix("HEAD -> ") {
+        spans.push(Span::styled("HEAD -> ", bold.fg(Color::Cyan)));
+        name = branch;
+    }
+    let (short, color) = if let Some(tag) = name.strip_prefix("tag: refs/tags/") {
+        (format!("tag: {}", tag), Color::Yellow)
+    } else if let Some(branch) = name.strip_prefix("refs/heads/") {
+        (branch.to_string(), Color::Green)
+    } else if let Some(remote) = name.strip_prefix("refs/remotes/") {
+        (remote.to_string(), Color::Red)
+    } else {
+        (name.to_string(), Color::Cyan)
+    };
+    spans.push(Span::styled(short, bold.fg(color)));
+    spans
+}
+
+/// Old and new versions of an image next to each other, each titled with its
+/// dimensions and size.
+fn render_image_diff(f: &mut Frame, images: &mut ImageDiff, block: Block, area: Rect) {
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let sides: Vec<(&str, &mut ImageSide)> =
+        [("Before", &mut images.old), ("After", &mut images.new)]
+            .into_iter()
+            .filter_map(|(name, side)| side.as_mut().map(|s| (name, s)))
+            .collect();
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Ratio(1, sides.len() as u32); sides.len()])
+        .split(inner);
+
+    for ((name, side), pane) in sides.into_iter().zip(panes.iter()) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray))
+            .title(format!(" {} ({}): {} ", name, side.label, side.summary()));
+        let area = block.inner(*pane);
+        f.render_widget(block, *pane);
+        f.render_widget(Paragraph::new(side.lines(area.width, area.height)), area);
+    }
+}
+
+/// Search bar shared by the diff and file views, shown while a query is typed or set.
+fn render_diff_search(f: &mut Frame, app: &App, area: Rect) {
+    if !app.is_diff_search && app.diff_search_query.is_empty() {
+        return;
+    }
+    let count_text = if app.diff_matches.is_empty() {
+        " (no matches) ".to_string()
+    } else {
+        format!(
+            " [{}/{}] ",
+            app.current_diff_match.map(|i| i + 1).unwrap_or(0),
+            app.diff_matches.len()
+        )
+    };
+
+    let search_bar = Paragraph::new(Line::from(vec![
+        Span::styled(" Search: ", Style::default().fg(Color::Yellow)),
+        Span::raw(&app.diff_search_query),
+        Span::styled(count_text, Style::default().fg(Color::DarkGray)),
+    ]))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+    f.render_widget(search_bar, area);
+
+    if app.is_diff_search {
+        f.set_cursor(
+            area.x + 10 + app.diff_search_query.width() as u16,
+            area.y + 1,
+        );
+    }
+}
+
+/// Layers the patch mode hunk and line selection over the style of raw diff line `i`.
+fn selection_style(app: &App, i: usize, mut style: Style) -> Style {
+    if !app.patch_mode {
+        return style;
+    }
+
+    let selected_hunk = app
+        .selected_hunk_idx
+        .and_then(|idx| app.diff_hunks.get(idx));
+    if let Some(hunk) = selected_hunk {
+        if i >= hunk.display_start && i <= hunk.display_end {
+            style = style
+                .bg(Color::Rgb(50, 50, 50))
+                .add_modifier(Modifier::BOLD);
+        } else {
+            style = style.add_modifier(Modifier::DIM); // Dim others
+        }
+    }
+
+    // Line selection inside the hunk
+    if app.line_mode {
+        if app.selected_lines.contains(&i) {
+            style = style.bg(Color::Rgb(40, 70, 100));
+        }
+        if i == app.line_cursor {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+    }
+    style
+}
+
+/// Display width of the widest raw diff line in the `height` rows from the
+/// one that holds `diff_scroll`.
+fn longest_diff_line(app: &App, height: usize) -> usize {
+    let model = &app.diff_model;
+    let start = side_by_side::row_at(&model.rows, app.diff_scroll);
+    model.rows[start.min(model.rows.len())..]
+        .iter()
+        .take(height)
+        .flat_map(|row| match row {
+            side_by_side::Row::Full(raw) => vec![*raw],
+            side_by_side::Row::Split { old, new } => {
+                [old, new].into_iter().flatten().map(|c| c.raw).collect()
+            }
+            side_by_side::Row::Folded(_) => Vec::new(),
+        })
+        .map(|raw| model.line(raw).width())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Unified diff with an old/new line-number gutter. Only the rows on screen,
+/// starting at the one that holds `diff_scroll`, are styled.
+fn render_unified(f: &mut Frame, app: &App, block: Block, area: Rect) {
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let model = &app.diff_model;
+    let start = side_by_side::row_at(&model.rows, app.diff_scroll);
+    let width = model.number_width;
+    let height = inner.height as usize;
+    let text_width = (inner.width as usize).saturating_sub(width * 2 + 3);
+    let gutter_style = Style::default().fg(Color::DarkGray);
+    let continuation = Span::styled(format!("{:>w$} │", "↪", w = width * 2 + 1), gutter_style);
+    let number = |n: Option<usize>| n.map(|n| n.to_string()).unwrap_or_default();
+
+    let mut lines = Vec::new();
+    for row in model.rows.iter().skip(start) {
+        if lines.len() >= height {
+            break;
+        }
+        let i = match row {
+            side_by_side::Row::Folded(range) => {
+                lines.push(folded_line(range, width * 2 + 2));
+                continue;
+            }
+            row => row.first_raw(),
+        };
+        let line = model.line(i);
+        let (old, new) = model.numbers[i];
+        let gutter = Span::styled(
+            format!("{:>w$} {:>w$} │", number(old), number(new), w = width),
+            gutter_style,
+        );
+
+        let (text, style) = styled_diff_line(line, &app.diff_folded);
+        let style = selection_style(app, i, style);
+        let syntax = syntax_colors(app, i);
+        let content = if syntax.is_empty() && !model.partners.contains_key(&i) {
+            vec![Span::styled(text, style)]
+        } else {
+            let ranges = word_emphasis(app, i);
+            let mut spans = vec![Span::styled(&line[..1], style)];
+            spans.extend(content_spans(&line[1..], style, syntax, &ranges));
+            spans
+        };
+        lines.extend(place_content(
+            app,
+            gutter,
+            &continuation,
+            content,
+            text_width,
+        ));
+    }
+    lines.truncate(height);
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Puts `content` after `gutter`, scrolled by `diff_hscroll`, or in wrap mode
+/// split at `width` columns with `continuation` as the gutter of later lines.
+fn place_content<'a>(
+    app: &App,
+    gutter: Span<'a>,
+    continuation: &Span<'a>,
+    content: Vec<Span<'a>>,
+    width: usize,
+) -> Vec<Line<'a>> {
+    let pieces = if app.diff_wrap {
+        wrap::wrap_spans(content, width)
+    } else {
+        vec![wrap::skip_columns(content, app.diff_hscroll)]
+    };
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(n, piece)| {
+            let mut spans = vec![if n == 0 {
+                gutter.clone()
+            } else {
+                continuation.clone()
+            }];
+            spans.extend(piece);
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Marker shown in place of folded unchanged lines, indented by `indent`.
+fn folded_line(range: &Range<usize>, indent: usize) -> Line<'static> {
+    Line::from(Span::styled(
+        format!("{}⋯ {} unchanged lines", " ".repeat(indent), range.len()),
+        Style::default()
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::ITALIC),
+    ))
+}
+
+/// Old and new columns with their own line-number gutters, scrolled together
+/// from the row that holds `diff_scroll`. Wrapped rows are padded so both
+/// columns stay aligned.
+fn render_side_by_side(f: &mut Frame, app: &App, block: Block, area: Rect) {
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(inner);
+
+    let model = &app.diff_model;
+    let start = side_by_side::row_at(&model.rows, app.diff_scroll);
+    let height = inner.height as usize;
+    // The new column loses one column to its border
+    let old_width = columns[0].width as usize;
+    let new_width = (columns[1].width as usize).saturating_sub(1);
+
+    let gutter_style = Style::default().fg(Color::DarkGray);
+    let continuation = Span::styled("    ↪ ", gutter_style);
+    let cell_lines = |cell: Option<side_by_side::Cell>, width: usize| -> Vec<Line> {
+        let Some(cell) = cell else {
+            return vec![Line::from(Span::styled("     ~", gutter_style))];
+        };
+        let raw = model.line(cell.raw);
+        let (_, style) = styled_diff_line(raw, &app.diff_folded);
+        let ranges = word_emphasis(app, cell.raw);
+        let content = content_spans(
+            raw.get(1..).unwrap_or(""),
+            selection_style(app, cell.raw, style),
+            syntax_colors(app, cell.raw),
+            &ranges,
+        );
+        let gutter = Span::styled(format!("{:>5} ", cell.line_no), gutter_style);
+        place_content(app, gutter, &continuation, content, width.saturating_sub(6))
+    };
+
+    let mut old_lines = Vec::new();
+    let mut new_lines = Vec::new();
+    for row in model.rows.iter().skip(start) {
+        if old_lines.len() >= height {
+            break;
+        }
+        let (mut old, mut new) = match row {
+            side_by_side::Row::Full(raw) => {
+                let (text, style) = styled_diff_line(model.line(*raw), &app.diff_folded);
+                let style = selection_style(app, *raw, style);
+                let none = Span::raw("");
+                // Separators and @@ headers mark both columns, other headers only the old one
+                let both = text.starts_with(['▸', '▾']) || text.starts_with("@@");
+                let new = if both {
+                    let content = vec![Span::styled(text.clone(), style)];
+                    place_content(app, none.clone(), &none, content, new_width)
+                } else {
+                    Vec::new()
+                };
+                let content = vec![Span::styled(text, style)];
+                (
+                    place_content(app, none.clone(), &none, content, old_width),
+                    new,
+                )
+            }
+            side_by_side::Row::Split { old, new } => {
+                (cell_lines(*old, old_width), cell_lines(*new, new_width))
+            }
+            side_by_side::Row::Folded(range) => {
+                (vec![folded_line(range, 6)], vec![folded_line(range, 6)])
+            }
+        };
+        let rows = old.len().max(new.len());
+        old.resize(rows, Line::from(""));
+        new.resize(rows, Line::from(""));
+        old_lines.extend(old);
+        new_lines.extend(new);
+    }
+    old_lines.truncate(height);
+    new_lines.truncate(height);
+
+    f.render_widget(Paragraph::new(old_lines), columns[0]);
+    f.render_widget(
+        Paragraph::new(new_lines).block(
+            Block::default()
+                .borders(Borders::LEFT)
+                .border_style(gutter_style),
+        ),
+        columns[1],
+    );
+}
+
+fn syntax_colors(app: &App, raw: usize) -> &[(Range<usize>, Color)] {
+    if app.syntax_highlight {
+        app.highlight.line(raw)
+    } else {
+        &[]
+    }
+}
+
+/// Word-level changes of raw diff line `raw` against its partner, as byte
+/// ranges into the line without its `+`/`-` marker.
+fn word_emphasis(app: &App, raw: usize) -> Vec<Range<usize>> {
+    let model = &app.diff_model;
+    let Some(&partner) = model.partners.get(&raw) else {
+        return Vec::new();
+    };
+    let line = model.line(raw);
+    let other = model.line(partner);
+    let is_old = line.starts_with('-');
+    let (old, new) = if is_old { (line, other) } else { (other, line) };
+
+    match word_diff::emphasize(&old[1..], &new[1..], app.word_regex.as_ref()) {
+        Some((old_ranges, new_ranges)) => {
+            if is_old {
+                old_ranges
+            } else {
+                new_ranges
+            }
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Splits the content of a diff line into spans: syntax colors (if any) under a
+/// red/green background tint, and a stronger background for the word-level
+/// `emphasis` ranges.
+fn content_spans<'a>(
+    text: &'a str,
+    style: Style,
+    syntax: &[(Range<usize>, Color)],
+    emphasis: &[Range<usize>],
+) -> Vec<Span<'a>> {
+    let (tint, strong) = match style.fg {
+        Some(Color::Red) => (Some(Color::Rgb(60, 20, 20)), Some(Color::Rgb(110, 30, 30))),
+        Some(Color::Green) => (Some(Color::Rgb(20, 50, 20)), Some(Color::Rgb(30, 90, 30))),
+        _ => (None, None),
+    };
+    let base = match tint {
+        Some(tint) if !syntax.is_empty() && style.bg.is_none() => style.bg(tint),
+        _ => style,
+    };
+
+    let mut bounds = vec![0, text.len()];
+    for range in syntax.iter().map(|(r, _)| r).chain(emphasis) {
+        bounds.extend([range.start, range.end]);
+    }
+    bounds.retain(|&b| b <= text.len());
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    bounds
+        .windows(2)
+        .map(|w| {
+            let mut seg = base;
+            if let Some((_, color)) = syntax.iter().find(|(r, _)| r.contains(&w[0])) {
+                seg = seg.fg(*color);
+            }
+            if emphasis.iter().any(|r| r.contains(&w[0])) {
+                if let Some(strong) = strong {
+                    seg = seg.bg(strong);
+                }
+                seg = seg.add_modifier(Modifier::BOLD);
+            }
+            Span::styled(&text[w[0]..w[1]], seg)
+        })
+        .collect()
+}
+
+/// Base styling of a raw diff line. `diff --git` lines are drawn as file
+/// separators (with their fold state) so multi-file diffs read as one stream.
+fn styled_diff_line<'a>(line: &'a str, folded: &HashSet<String>) -> (Cow<'a, str>, Style) {
+    if let Some(path) = crate::git::patch::path_from_diff_header(line) {
+        let marker = if folded.contains(path) { '▸' } else { '▾' };
+        return (
+            Cow::Owned(format!("{} {} ━━━", marker, path)),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+    }
+
+    let style = if line.starts_with('+') {
+        Style::default().fg(Color::Green)
+    } else if line.starts_with('-') {
+        Style::default().fg(Color::Red)
+    } else if line.starts_with("@@") {
+        Style::default().fg(Color::Cyan)
+    } else {
+        Style::default()
+    };
+    (Cow::Borrowed(line), style)
+}
+
 fn render_commit_dialog(f: &mut Frame, app: &mut App) {
     let area = centered_rect(50, 20, f.size());
     let block = Block::default()
@@ -368,6 +1294,126 @@ fn render_commit_dialog(f: &mut Frame, app: &mut App) {
     f.render_widget(p, area);
 }
 
+fn render_discard_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 40, f.size());
+    let mut text = Vec::new();
+
+    match &app.pending_discard {
+        Some(DiscardRequest::Files(changes)) => {
+            text.push(Line::from(format!(
+                "Discard working-tree changes in {} file(s)?",
+                changes.len()
+            )));
+            text.push(Line::from(""));
+            for change in changes {
+                let (label, color) = if change.is_untracked {
+                    ("delete ", Color::Red)
+                } else {
+                    ("restore", Color::Yellow)
+                };
+                text.push(Line::from(vec![
+                    Span::styled(format!("  {} ", label), Style::default().fg(color)),
+                    Span::raw(change.path.as_str()),
+                ]));
+            }
+        }
+        Some(DiscardRequest::Hunk(i)) => {
+            text.push(Line::from(format!(
+                "Discard hunk {} of {}?",
+                i + 1,
+                app.diff_hunks
+                    .get(*i)
+                    .and_then(|h| app.diff_files.get(h.file))
+                    .map(|f| f.path.as_str())
+                    .unwrap_or("")
+            )));
+        }
+        None => return,
+    }
+
+    text.push(Line::from(""));
+    text.push(Line::from(vec![
+        Span::styled("y/Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to discard (undo with u), "),
+        Span::styled("n/Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to cancel"),
+    ]));
+
+    let block = Block::default()
+        .title(" Discard Changes ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red));
+
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(
+        Paragraph::new(text)
+            .block(block)
+            .wrap(ratatui::widgets::Wrap { trim: false }),
+        area,
+    );
+}
+
+fn render_ignore_dialog(f: &mut Frame, app: &mut App) {
+    let area = centered_rect(60, 60, f.size());
+    f.render_widget(ratatui::widgets::Clear, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(8), Constraint::Min(0)])
+        .split(area);
+
+    let items: Vec<ListItem> = app
+        .ignore_candidates
+        .iter()
+        .map(|c| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<14}", c.label), Style::default().fg(Color::Cyan)),
+                Span::raw(c.patterns.join(", ")),
+            ]))
+        })
+        .collect();
+
+    let title = format!(
+        " Add to {} (Tab: target, Enter: add, Esc: cancel) ",
+        app.ignore_target.as_str()
+    );
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(list, chunks[0], &mut app.ignore_state);
+
+    let preview: Vec<Line> = app
+        .ignore_preview
+        .iter()
+        .map(|p| {
+            Line::from(Span::styled(
+                p.as_str(),
+                Style::default().fg(Color::DarkGray),
+            ))
+        })
+        .collect();
+    let preview_title = format!(" Would hide {} untracked files ", app.ignore_preview.len());
+    f.render_widget(
+        Paragraph::new(preview).block(
+            Block::default()
+                .title(preview_title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        ),
+        chunks[1],
+    );
+}
+
 fn render_worktree_selector(f: &mut Frame, app: &mut App) {
     let area = centered_rect(60, 40, f.size());
     let items: Vec<ListItem> = app
@@ -411,6 +1457,129 @@ fn render_worktree_selector(f: &mut Frame, app: &mut App) {
     f.render_stateful_widget(list, area, &mut app.worktree_state);
 }
 
+fn render_branch_modal(f: &mut Frame, app: &mut App) {
+    let Some(modal) = app.branch_modal.as_mut() else {
+        return;
+    };
+    let area = centered_rect(80, 60, f.size());
+
+    let name_width = modal
+        .branches
+        .iter()
+        .map(|b| b.name.width())
+        .max()
+        .unwrap_or(0)
+        .min(32);
+    let tracking: Vec<String> = modal
+        .branches
+        .iter()
+        .map(|b| match &b.upstream {
+            Some(upstream) if b.upstream_gone => format!("{} gone", upstream),
+            Some(upstream) => {
+                let mut text = upstream.clone();
+                if b.ahead > 0 {
+                    text.push_str(&format!(" ↑{}", b.ahead));
+                }
+                if b.behind > 0 {
+                    text.push_str(&format!(" ↓{}", b.behind));
+                }
+                text
+            }
+            None => String::new(),
+        })
+        .collect();
+    let tracking_width = tracking.iter().map(|t| t.width()).max().unwrap_or(0);
+
+    let items: Vec<ListItem> = modal
+        .branches
+        .iter()
+        .zip(&tracking)
+        .map(|(branch, tracking)| {
+            let marker = if branch.is_head { "* " } else { "  " };
+            let mut name_style = Style::default().fg(if branch.is_remote {
+                Color::Red
+            } else {
+                Color::Green
+            });
+            if branch.is_head {
+                name_style = name_style.add_modifier(Modifier::BOLD);
+            }
+            let name: String = branch.name.chars().take(name_width).collect();
+            let tracking_color = if branch.upstream_gone {
+                Color::Red
+            } else if branch.behind > 0 {
+                Color::Yellow
+            } else {
+                Color::Cyan
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(marker, Style::default().fg(Color::Green)),
+                Span::styled(format!("{:<w$} ", name, w = name_width), name_style),
+                Span::styled(
+                    format!("{:<w$} ", tracking, w = tracking_width),
+                    Style::default().fg(tracking_color),
+                ),
+                Span::styled(
+                    format!("{} ", branch.date),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(branch.subject.clone()),
+            ]))
+        })
+        .collect();
+
+    let footer = if let Some((input, text)) = &modal.input {
+        let prompt = match input {
+            BranchInput::Create { start } => format!("New branch from {}", start),
+            BranchInput::Rename { old } => format!("Rename {} to", old),
+            BranchInput::Upstream { branch } => format!("Upstream of {}", branch),
+        };
+        Span::styled(
+            format!(" {}: {}█ ", prompt, text),
+            Style::default().fg(Color::White),
+        )
+    } else if let Some(confirm) = &modal.confirm {
+        let question = match confirm {
+            BranchConfirm::Checkout => {
+                "Tracked files have local changes. Check out anyway? (y/n)".to_string()
+            }
+            BranchConfirm::Delete { name, merged: true } => format!("Delete {}? (y/n)", name),
+            BranchConfirm::Delete { name, .. } => {
+                format!("{} is not merged into HEAD. Delete anyway? (y/n)", name)
+            }
+        };
+        Span::styled(
+            format!(" {} ", question),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        )
+    } else if let Some(msg) = &app.status_message {
+        Span::styled(format!(" {} ", msg), Style::default().fg(Color::Yellow))
+    } else {
+        Span::styled(
+            " Enter: Checkout | n/N: New from HEAD/Selected | r: Rename | u: Upstream | d: Delete ",
+            Style::default().fg(Color::DarkGray),
+        )
+    };
+
+    let block = Block::default()
+        .title(" Branches ")
+        .title_bottom(footer)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol(">> ");
+
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_stateful_widget(list, area, &mut modal.state);
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -489,7 +1658,13 @@ fn render_bottom_bar(f: &mut Frame, app: &App, area: Rect) {
             ));
         }
 
-        let left_content = Line::from(stats_spans);
+        let left_content = match &app.status_message {
+            Some(msg) => Line::from(Span::styled(
+                format!(" {}", msg),
+                Style::default().fg(Color::Yellow),
+            )),
+            None => Line::from(stats_spans),
+        };
         let right_content = Line::from(vec![
             Span::raw(" ["),
             Span::styled("?", Style::default().fg(Color::Yellow)),
@@ -528,6 +1703,7 @@ fn render_list(
     current_focus: Focus,
     visual_range: Option<(usize, usize)>,
     is_easter_egg: bool,
+    ignore_rules: &HashMap<String, String>,
 ) {
     let items: Vec<ListItem> = nodes
         .iter()
@@ -559,6 +1735,8 @@ fn render_list(
                 Span::styled("[+]", Style::default().fg(Color::Green))
             } else if node.status == '?' {
                 Span::styled("[?]", Style::default().fg(Color::Red))
+            } else if node.status == '!' {
+                Span::styled("[!]", Style::default().fg(Color::DarkGray))
             } else if node.status == ' ' {
                 Span::raw("   ")
             } else {
@@ -570,6 +1748,10 @@ fn render_list(
                 Style::default()
                     .add_modifier(Modifier::BOLD)
                     .fg(theme.color_dir)
+            } else if node.status == '!' {
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::DIM)
             } else {
                 Style::default().fg(theme.color_file)
             };
@@ -637,6 +1819,13 @@ fn render_list(
                 }
             }
 
+            if let Some(rule) = ignore_rules.get(&node.full_path) {
+                spans.push(Span::styled(
+                    format!("{}  {}", padding, rule),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+
             ListItem::new(Line::from(spans)).style(item_style)
         })
         .collect();
@@ -0,0 +1,173 @@
+use regex::Regex;
+use std::ops::Range;
+
+// Pairs with more token comparisons than this are left unemphasized
+const MAX_CELLS: usize = 250_000;
+
+/// Changed byte ranges of the old and the new line.
+pub type Emphasis = (Vec<Range<usize>>, Vec<Range<usize>>);
+
+/// Splits `line` into tokens. With git's `diff.wordRegex` every match is a token
+/// and the text between matches forms one token per gap; otherwise runs of word
+/// characters, runs of whitespace and single punctuation characters are tokens.
+fn tokenize(line: &str, word_regex: Option<&Regex>) -> Vec<Range<usize>> {
+    let mut tokens = Vec::new();
+
+    if let Some(re) = word_regex {
+        let mut last = 0;
+        for m in re.find_iter(line).filter(|m| !m.is_empty()) {
+            if m.start() > last {
+                tokens.push(last..m.start());
+            }
+            tokens.push(m.range());
+            last = m.end();
+        }
+        if last < line.len() {
+            tokens.push(last..line.len());
+        }
+        return tokens;
+    }
+
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    };
+
+    let mut start = 0;
+    let mut current: Option<Class> = None;
+    for (i, c) in line.char_indices() {
+        let cls = class(c);
+        let extends = current.as_ref() == Some(&cls) && cls != Class::Other;
+        if !extends && i > start {
+            tokens.push(start..i);
+            start = i;
+        }
+        current = Some(cls);
+    }
+    if start < line.len() {
+        tokens.push(start..line.len());
+    }
+    tokens
+}
+
+/// Byte ranges of `old` and `new` that differ, found with a token-level LCS.
+/// Returns `None` when the lines have nothing but whitespace in common, since
+/// emphasizing everything would only add noise.
+pub fn emphasize(old: &str, new: &str, word_regex: Option<&Regex>) -> Option<Emphasis> {
+    let old_tokens = tokenize(old, word_regex);
+    let new_tokens = tokenize(new, word_regex);
+    let (n, m) = (old_tokens.len(), new_tokens.len());
+    if n == 0 || m == 0 || (n + 1) * (m + 1) > MAX_CELLS {
+        return None;
+    }
+
+    let old_text = |i: usize| &old[old_tokens[i].clone()];
+    let new_text = |j: usize| &new[new_tokens[j].clone()];
+
+    // lcs[i][j]: length of the LCS of old_tokens[i..] and new_tokens[j..]
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_text(i) == new_text(j) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut old_common = vec![false; n];
+    let mut new_common = vec![false; m];
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old_text(i) == new_text(j) {
+            old_common[i] = true;
+            new_common[j] = true;
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    let shares_words = old_common
+        .iter()
+        .zip(&old_tokens)
+        .any(|(&common, r)| common && !old[r.clone()].trim().is_empty());
+    if !shares_words {
+        return None;
+    }
+
+    Some((
+        changed_ranges(&old_tokens, &old_common),
+        changed_ranges(&new_tokens, &new_common),
+    ))
+}
+
+/// Merges adjacent unmatched tokens into ranges.
+fn changed_ranges(tokens: &[Range<usize>], common: &[bool]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (token, &is_common) in tokens.iter().zip(common) {
+        if is_common {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(last) if last.end == token.start => last.end = token.end,
+            _ => ranges.push(token.clone()),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emphasize_single_word() {
+        let (old, new) = emphasize("let x = foo(1);", "let x = bar(1);", None).unwrap();
+        assert_eq!(old, vec![8..11]);
+        assert_eq!(new, vec![8..11]);
+    }
+
+    #[test]
+    fn test_emphasize_insertion() {
+        let (old, new) = emphasize("a b", "a c b", None).unwrap();
+        assert!(old.is_empty());
+        assert_eq!(new, vec![2..4]);
+    }
+
+    #[test]
+    fn test_emphasize_nothing_in_common() {
+        assert!(emphasize("foo", "bar", None).is_none());
+    }
+
+    #[test]
+    fn test_emphasize_word_regex() {
+        // Characters as words: only the changed digit is emphasized
+        let re = Regex::new(r"[^[:space:]]").unwrap();
+        let (old, new) = emphasize("value1", "value2", Some(&re)).unwrap();
+        assert_eq!(old, vec![5..6]);
+        assert_eq!(new, vec![5..6]);
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let line = "fn a_b(x)";
+        let tokens: Vec<&str> = tokenize(line, None).into_iter().map(|r| &line[r]).collect();
+        assert_eq!(tokens, vec!["fn", " ", "a_b", "(", "x", ")"]);
+    }
+}
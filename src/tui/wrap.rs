@@ -0,0 +1,103 @@
+use ratatui::text::Span;
+use unicode_width::UnicodeWidthChar;
+
+/// Drops the first `columns` display columns of `spans`, for horizontal
+/// scrolling. A wide character cut in half becomes a space.
+pub fn skip_columns(spans: Vec<Span<'_>>, columns: usize) -> Vec<Span<'_>> {
+    if columns == 0 {
+        return spans;
+    }
+    let mut col = 0;
+    let mut out = Vec::new();
+    for span in spans {
+        if col >= columns {
+            out.push(span);
+            continue;
+        }
+        let mut kept = String::new();
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            if col >= columns {
+                kept.push(c);
+            } else if col + w > columns {
+                kept.push(' ');
+            }
+            col += w;
+        }
+        if !kept.is_empty() {
+            out.push(Span::styled(kept, span.style));
+        }
+    }
+    out
+}
+
+/// Splits `spans` into lines of at most `width` display columns, keeping
+/// their styles. Always returns at least one (possibly empty) line.
+pub fn wrap_spans(spans: Vec<Span<'_>>, width: usize) -> Vec<Vec<Span<'_>>> {
+    let mut lines = vec![Vec::new()];
+    if width == 0 {
+        lines[0] = spans;
+        return lines;
+    }
+    let mut col = 0;
+    for span in spans {
+        let mut piece = String::new();
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            if col + w > width && col > 0 {
+                if !piece.is_empty() {
+                    let text = std::mem::take(&mut piece);
+                    lines
+                        .last_mut()
+                        .unwrap()
+                        .push(Span::styled(text, span.style));
+                }
+                lines.push(Vec::new());
+                col = 0;
+            }
+            piece.push(c);
+            col += w;
+        }
+        if !piece.is_empty() {
+            lines
+                .last_mut()
+                .unwrap()
+                .push(Span::styled(piece, span.style));
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::{Color, Style};
+
+    fn text(spans: &[Span]) -> String {
+        spans.iter().map(|s| s.content.as_ref()).collect()
+    }
+
+    #[test]
+    fn test_skip_columns() {
+        let red = Style::default().fg(Color::Red);
+        let spans = vec![Span::raw("+ab"), Span::styled("cdef", red)];
+        let out = skip_columns(spans, 4);
+        assert_eq!(text(&out), "def");
+        assert_eq!(out[0].style, red);
+
+        // The wide character is cut in half
+        assert_eq!(text(&skip_columns(vec![Span::raw("a漢b")], 2)), " b");
+    }
+
+    #[test]
+    fn test_wrap_spans() {
+        let red = Style::default().fg(Color::Red);
+        let spans = vec![Span::raw("+abc"), Span::styled("defgh", red)];
+        let lines = wrap_spans(spans, 4);
+        let texts: Vec<String> = lines.iter().map(|l| text(l)).collect();
+        assert_eq!(texts, vec!["+abc", "defg", "h"]);
+        assert_eq!(lines[2][0].style, red);
+
+        assert_eq!(wrap_spans(Vec::new(), 4).len(), 1);
+    }
+}